@@ -1,133 +1,554 @@
+use bevy::app::TaskPoolThreadAssignmentPolicy;
 use bevy::asset::RenderAssetUsages;
+use bevy::image::ImageSampler;
+use bevy::input::mouse::{AccumulatedMouseMotion, AccumulatedMouseScroll, MouseScrollUnit};
 use bevy::prelude::*;
 use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+use bevy::tasks::{AsyncComputeTaskPool, Task, block_on, poll_once};
+use bevy::window::{PrimaryWindow, WindowResolution};
+use clap::Parser;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
-use scene_loader::load_scene;
-use scene_types::{MaterialDef, SceneFile};
+use scene_loader::animation::build_frame;
+use scene_loader::cli::parse_resolution;
+use scene_loader::load_scene_with_warnings;
 
-use ray_tracer::lighting::{Light, lighting};
-use ray_tracer::math::{Ray, Tuple};
-use ray_tracer::shape::{Color, Sphere};
+use ray_tracer::math::Tuple;
+use ray_tracer::scene::canvas::{DisplayTransform, ToneOperator};
+use ray_tracer::scene::{Camera, Canvas, RenderSettings, Tile, World};
+use ray_tracer::shape::Color;
+
+/// The width and height of the tiles rendered in the background, in pixels.
+const TILE_SIZE: usize = 32;
+/// The longer side of the preview rendered while the camera moves, in pixels.
+const PREVIEW_SIZE: usize = 128;
+/// How long the controls must rest before the full render starts.
+const SETTLE_TIME: Duration = Duration::from_millis(250);
+/// The orbit angle per pixel of mouse motion, in radians.
+const ORBIT_PER_PIXEL: f32 = 0.005;
+/// The orbit speed of the arrow keys, in radians per second.
+const ORBIT_PER_SECOND: f32 = 1.5;
+/// The share of the distance to the target dollied per scroll line.
+const DOLLY_PER_LINE: f32 = 0.1;
+/// The share of the field of view zoomed per scroll line.
+const ZOOM_PER_LINE: f32 = 0.05;
+/// The scroll lines per second the keys dolly and zoom by.
+const LINES_PER_SECOND: f32 = 5.0;
+
+/// Shows a scene file rendered by the ray tracer.
+///
+/// Drag with the left mouse button or press the arrow keys to orbit around
+/// the camera target, drag with the right button to pan, scroll or press
+/// W and S to dolly, and hold Ctrl while scrolling or press - and = to zoom.
+/// A low-resolution preview follows the camera; the full render starts when
+/// the controls rest.
+#[derive(Parser)]
+#[command(version)]
+struct Args {
+    /// The scene file, in TOML, JSON or YAML.
+    #[arg(default_value = "scene/example.toml")]
+    scene: PathBuf,
+
+    /// The image size in pixels, as WIDTHxHEIGHT.
+    #[arg(short, long, value_parser = parse_resolution, default_value = "400x400")]
+    resolution: (usize, usize),
+
+    /// The rays traced per pixel.
+    #[arg(short, long, default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..))]
+    spp: u32,
+
+    /// The longest chain of secondary rays, such as reflections.
+    #[arg(long, default_value_t = RenderSettings::default().max_depth)]
+    max_depth: u32,
+
+    /// The threads to render with, or 0 for one per core.
+    #[arg(short = 'j', long, default_value_t = 0)]
+    threads: usize,
+
+    /// Seeds the placement of the samples in each pixel.
+    #[arg(long, default_value_t = 0)]
+    seed: u64,
+}
 
 fn main() {
-    let scene = match load_scene("scene/example.toml") {
-        Ok(s) => s,
+    let args = Args::parse();
+    let scene = match load_scene_with_warnings(&args.scene) {
+        Ok((s, warnings)) => {
+            for warning in warnings {
+                eprintln!("warning: {warning}");
+            }
+            s
+        }
         Err(e) => {
-            eprintln!("Failed to load scene: {e}");
+            eprintln!("Failed to load scene {}: {e}", args.scene.display());
             std::process::exit(1);
         }
     };
+    // the same world and camera as `scene render`; animated scenes show their
+    // first frame
+    let render = match build_frame(&scene, 1) {
+        Ok(render) => render,
+        Err(e) => {
+            eprintln!("Failed to build scene {}: {e}", args.scene.display());
+            std::process::exit(1);
+        }
+    };
+    let (width, height) = args.resolution;
+    let settings = RenderSettings {
+        width,
+        height,
+        samples: args.spp,
+        max_depth: args.max_depth,
+        threads: args.threads,
+        seed: args.seed,
+    };
+
+    // tiles are traced on the async compute pool, one thread per render thread
+    let threads = settings.thread_count();
+    let task_pool_options = TaskPoolOptions {
+        async_compute: TaskPoolThreadAssignmentPolicy {
+            min_threads: threads,
+            max_threads: threads,
+            percent: 1.0,
+            on_thread_spawn: None,
+            on_thread_destroy: None,
+        },
+        ..default()
+    };
 
     App::new()
-        .add_plugins(DefaultPlugins)
-        .insert_resource(Scene(scene))
+        .add_plugins(
+            DefaultPlugins
+                .set(WindowPlugin {
+                    primary_window: Some(Window {
+                        title: format!("{} - ray tracer", args.scene.display()),
+                        resolution: WindowResolution::new(width as u32, height as u32),
+                        ..default()
+                    }),
+                    ..default()
+                })
+                .set(TaskPoolPlugin { task_pool_options }),
+        )
+        .insert_resource(View::new(&render.camera))
+        .insert_resource(Scene(Arc::new(render.world)))
+        .insert_resource(Settings(settings))
         .add_systems(Startup, setup)
+        .add_systems(
+            Update,
+            (
+                control_view,
+                preview_view,
+                render_view,
+                receive_tiles,
+                show_image,
+                show_progress,
+                fit_to_window,
+            )
+                .chain(),
+        )
         .run();
 }
 
 #[derive(Resource)]
-struct Scene(SceneFile);
-
-fn setup(mut commands: Commands, scene: Res<Scene>, mut images: ResMut<Assets<Image>>) {
-    let n: usize = 400;
-
-    let scene = &scene.0;
-
-    let ray_orig = Tuple::point(
-        scene.camera.position[0] as f64,
-        scene.camera.position[1] as f64,
-        scene.camera.position[2] as f64,
-    );
-    let mut sphere = Sphere::new();
-    let color_base = Color::new(
-        scene.objects[0].color[0] as f64 / 255.0,
-        scene.objects[0].color[1] as f64 / 255.0,
-        scene.objects[0].color[2] as f64 / 255.0,
-    );
-    sphere.material.color = color_base;
-    match &scene.objects[0].material {
-        MaterialDef::Default(_) => {
-            sphere.material.ambient = 0.1;
-            sphere.material.diffuse = 0.9;
-            sphere.material.specular = 0.9;
-            sphere.material.shininess = 90.0;
+struct Scene(Arc<World>);
+
+#[derive(Resource)]
+struct Settings(RenderSettings);
+
+/// The camera the scene is viewed from, moved by the controls.
+#[derive(Resource)]
+struct View {
+    position: Vec3,
+    target: Vec3,
+    /// The vertical field of view, in degrees.
+    fov: f32,
+    /// When the view last moved, while the full render waits for the
+    /// controls to rest.
+    moved: Option<Instant>,
+}
+
+impl View {
+    fn new(camera: &Camera) -> Self {
+        let vec3 = |t: Tuple| Vec3::new(t.x as f32, t.y as f32, t.z as f32);
+        View {
+            position: vec3(camera.get_position()),
+            target: vec3(camera.get_target()),
+            fov: camera.get_fov() as f32,
+            moved: None,
         }
-        MaterialDef::Custom(m) => {
-            sphere.material.ambient = m.ambient_coeff as f64;
-            sphere.material.diffuse = m.diffuse_coeff as f64;
-            sphere.material.specular = m.specular_coeff as f64;
-            sphere.material.shininess = m.shininess as f64;
+    }
+
+    fn camera(&self) -> Camera {
+        let point = |v: Vec3| Tuple::point(v.x as f64, v.y as f64, v.z as f64);
+        Camera::new(point(self.position), point(self.target), self.fov as f64)
+    }
+
+    /// Turns the camera around the target, keeping its distance. The pitch
+    /// stops short of the poles, where the camera would flip.
+    fn orbit(&mut self, yaw: f32, pitch: f32) {
+        let offset = self.position - self.target;
+        let radius = offset.length();
+        if radius <= f32::EPSILON {
+            return;
         }
-    };
+        let max_pitch = 89f32.to_radians();
+        let yaw = offset.x.atan2(offset.z) + yaw;
+        let pitch =
+            ((offset.y / radius).clamp(-1.0, 1.0).asin() + pitch).clamp(-max_pitch, max_pitch);
+        let (sin_yaw, cos_yaw) = yaw.sin_cos();
+        let (sin_pitch, cos_pitch) = pitch.sin_cos();
+        self.position =
+            self.target + radius * Vec3::new(cos_pitch * sin_yaw, sin_pitch, cos_pitch * cos_yaw);
+    }
+
+    /// Moves the camera towards the target by `lines` scroll lines, or away
+    /// from it when negative.
+    fn dolly(&mut self, lines: f32) {
+        let offset = self.position - self.target;
+        let scaled = offset * (1.0 - DOLLY_PER_LINE).powf(lines);
+        self.position = self.target + scaled.clamp_length_min(1e-3);
+    }
+
+    /// Moves the camera and the target across the view, so that the point
+    /// of the target follows a drag of `delta` pixels in a window
+    /// `height` pixels high.
+    fn pan(&mut self, delta: Vec2, height: f32) {
+        let offset = self.target - self.position;
+        let forward = offset.normalize_or_zero();
+        // the ray tracer's view space is left-handed
+        let right = Vec3::Y.cross(forward).normalize_or_zero();
+        let up = forward.cross(right);
+        let per_pixel = 2.0 * offset.length() * (self.fov.to_radians() / 2.0).tan() / height;
+        let shift = (up * delta.y - right * delta.x) * per_pixel;
+        self.position += shift;
+        self.target += shift;
+    }
+
+    /// Narrows the field of view by `lines` scroll lines, or widens it when
+    /// negative.
+    fn zoom(&mut self, lines: f32) {
+        self.fov = (self.fov * (1.0 - ZOOM_PER_LINE).powf(lines)).clamp(1.0, 170.0);
+    }
+}
+
+/// The image being rendered: either a preview, or the tiles of the full
+/// render still being traced and the canvas the finished ones are copied
+/// into.
+#[derive(Resource)]
+struct RenderJob {
+    tasks: Vec<Task<(Tile, Vec<Color>)>>,
+    tiles: usize,
+    canvas: Canvas,
+    image: Handle<Image>,
+    preview: bool,
+    started: Instant,
+    took: Option<Duration>,
+}
+
+impl RenderJob {
+    /// Starts tracing the tiles of `camera`'s view in the background, from
+    /// the center of the image outwards, into a new black image.
+    fn start(
+        world: &Arc<World>,
+        camera: Camera,
+        settings: RenderSettings,
+        images: &mut Assets<Image>,
+    ) -> Self {
+        let (width, height) = (settings.width, settings.height);
+        let mut canvas = Canvas::new(width, height);
+        canvas.set_display_transform(DisplayTransform::srgb(ToneOperator::Clamp));
+        let image = images.add(texture(&canvas));
+
+        let mut tiles = settings.tiles(TILE_SIZE);
+        let center = |tile: &Tile| {
+            let dx = (tile.x + tile.width / 2) as f64 - width as f64 / 2.0;
+            let dy = (tile.y + tile.height / 2) as f64 - height as f64 / 2.0;
+            dx * dx + dy * dy
+        };
+        tiles.sort_by(|a, b| center(a).total_cmp(&center(b)));
+
+        let pool = AsyncComputeTaskPool::get();
+        let camera = Arc::new(camera);
+        let tasks = tiles
+            .iter()
+            .map(|&tile| {
+                let world = Arc::clone(world);
+                let camera = Arc::clone(&camera);
+                pool.spawn(async move {
+                    let colors = camera.render_tile(&world, tile, &settings);
+                    (tile, colors)
+                })
+            })
+            .collect();
+
+        RenderJob {
+            tasks,
+            tiles: tiles.len(),
+            canvas,
+            image,
+            preview: false,
+            started: Instant::now(),
+            took: None,
+        }
+    }
 
-    let light = Light::point_light(
-        Tuple::point(
-            scene.lights[0].position[0] as f64,
-            scene.lights[0].position[1] as f64,
-            scene.lights[0].position[2] as f64,
-        ),
-        Color::new(
-            scene.lights[0].color[0] as f64 / 255.0,
-            scene.lights[0].color[1] as f64 / 255.0,
-            scene.lights[0].color[2] as f64 / 255.0,
-        ),
-    );
-
-    // camera/wall
-    let wall_z = 10.0;
-    let wall_size = 7.0;
-    let pixel_size = wall_size / n as f64;
-    let half = wall_size / 2.0;
-
-    // pixels (RGBA8)
-    let mut rgba = vec![0u8; n * n * 4];
-
-    for y in 0..n {
-        let world_y = half - pixel_size * y as f64;
-        for x in 0..n {
-            let world_x = -half + pixel_size * x as f64;
-
-            let pos = Tuple::point(world_x, world_y, wall_z);
-            let dir = (pos - ray_orig).normalize();
-            let ray = Ray::new(ray_orig, dir);
-
-            let hit = sphere.intersect(ray).into_iter().find(|t| *t >= 0.0);
-
-            let c = if let Some(t) = hit {
-                let p = ray.position(t);
-                let normal = sphere.normal_at(p);
-                let eye = -ray.direction;
-                lighting(&sphere.material, &light, &p, &eye, &normal)
-            } else {
-                Color::new(0.0, 0.0, 0.0)
-            };
-
-            let i = (y * n + x) * 4;
-            rgba[i] = c.get_clamped_red_u8();
-            rgba[i + 1] = c.get_clamped_green_u8();
-            rgba[i + 2] = c.get_clamped_blue_u8();
-            rgba[i + 3] = 255;
+    /// Renders `camera`'s view right away at a low resolution, with one
+    /// sample per pixel. Its pixels are shown as blocks, not blurred.
+    fn preview(
+        world: &World,
+        camera: Camera,
+        settings: RenderSettings,
+        images: &mut Assets<Image>,
+    ) -> Self {
+        let scale = PREVIEW_SIZE as f64 / settings.width.max(settings.height) as f64;
+        let size = |pixels: usize| ((pixels as f64 * scale.min(1.0)).round() as usize).max(1);
+        let preview = RenderSettings {
+            width: size(settings.width),
+            height: size(settings.height),
+            samples: 1,
+            ..settings
+        };
+        let started = Instant::now();
+        let mut canvas = camera.render_with(world, &preview);
+        canvas.set_display_transform(DisplayTransform::srgb(ToneOperator::Clamp));
+        let mut image = texture(&canvas);
+        image.sampler = ImageSampler::nearest();
+
+        RenderJob {
+            tasks: Vec::new(),
+            tiles: 0,
+            canvas,
+            image: images.add(image),
+            preview: true,
+            started,
+            took: Some(started.elapsed()),
         }
     }
+}
 
-    let image = Image::new(
+/// Creates a texture showing the display values of `canvas`.
+fn texture(canvas: &Canvas) -> Image {
+    Image::new(
         Extent3d {
-            width: n as u32,
-            height: n as u32,
+            width: canvas.width as u32,
+            height: canvas.height as u32,
             depth_or_array_layers: 1,
         },
         TextureDimension::D2,
-        rgba,
+        // the jobs encode their canvas for sRGB, to match the format
+        canvas.to_rgba8(),
         TextureFormat::Rgba8UnormSrgb,
         RenderAssetUsages::default(),
-    );
+    )
+}
 
-    let image_handle = images.add(image);
+/// Marks the sprite showing the rendered image.
+#[derive(Component)]
+struct RenderView;
+
+/// Marks the text showing how far the render is.
+#[derive(Component)]
+struct ProgressText;
+
+/// Opens the window and starts the full render of the scene's camera.
+fn setup(
+    mut commands: Commands,
+    scene: Res<Scene>,
+    view: Res<View>,
+    settings: Res<Settings>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    let settings = settings.0;
+    let job = RenderJob::start(&scene.0, view.camera(), settings, &mut images);
 
     commands.spawn(Camera2d);
-    commands.spawn(Sprite {
-        image: image_handle,
-        custom_size: Some(Vec2::new(n as f32, n as f32)),
-        ..default()
-    });
+    commands.spawn((
+        Sprite {
+            image: job.image.clone(),
+            custom_size: Some(Vec2::new(settings.width as f32, settings.height as f32)),
+            ..default()
+        },
+        RenderView,
+    ));
+    commands.spawn((
+        Text::new("Rendering 0%"),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(8.0),
+            left: Val::Px(8.0),
+            ..default()
+        },
+        ProgressText,
+    ));
+    commands.insert_resource(job);
+}
+
+/// Moves the view with the mouse and keyboard.
+fn control_view(
+    mut view: ResMut<View>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    keys: Res<ButtonInput<KeyCode>>,
+    motion: Res<AccumulatedMouseMotion>,
+    scroll: Res<AccumulatedMouseScroll>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    time: Res<Time>,
+) {
+    let seconds = time.delta_secs();
+    let held = |key: KeyCode| if keys.pressed(key) { 1.0 } else { 0.0 };
+
+    let mut orbit = Vec2::new(
+        held(KeyCode::ArrowLeft) - held(KeyCode::ArrowRight),
+        held(KeyCode::ArrowUp) - held(KeyCode::ArrowDown),
+    ) * ORBIT_PER_SECOND
+        * seconds;
+    if mouse.pressed(MouseButton::Left) {
+        orbit += Vec2::new(-motion.delta.x, motion.delta.y) * ORBIT_PER_PIXEL;
+    }
+    let pan = match mouse.pressed(MouseButton::Right) {
+        true => motion.delta,
+        false => Vec2::ZERO,
+    };
+    let lines = match scroll.unit {
+        MouseScrollUnit::Line => scroll.delta.y,
+        MouseScrollUnit::Pixel => scroll.delta.y / 100.0,
+    };
+    let ctrl = keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]);
+    let (mut dolly, mut zoom) = match ctrl {
+        true => (0.0, lines),
+        false => (lines, 0.0),
+    };
+    dolly += (held(KeyCode::KeyW) - held(KeyCode::KeyS)) * LINES_PER_SECOND * seconds;
+    zoom += (held(KeyCode::Equal) - held(KeyCode::Minus)) * LINES_PER_SECOND * seconds;
+
+    if orbit == Vec2::ZERO && pan == Vec2::ZERO && dolly == 0.0 && zoom == 0.0 {
+        return;
+    }
+    let height = windows.iter().next().map_or(1.0, |window| window.height());
+    view.orbit(orbit.x, orbit.y);
+    view.pan(pan, height);
+    view.dolly(dolly);
+    view.zoom(zoom);
+    view.moved = Some(Instant::now());
+}
+
+/// Replaces the image by a preview whenever the view moves.
+fn preview_view(
+    mut commands: Commands,
+    view: Res<View>,
+    scene: Res<Scene>,
+    settings: Res<Settings>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    if !view.is_changed() || view.moved.is_none() {
+        return;
+    }
+    let job = RenderJob::preview(&scene.0, view.camera(), settings.0, &mut images);
+    // the tasks of the replaced render are cancelled when it is dropped
+    commands.insert_resource(job);
+}
+
+/// Starts the full render once the view has rested for `SETTLE_TIME`.
+fn render_view(
+    mut commands: Commands,
+    mut view: ResMut<View>,
+    scene: Res<Scene>,
+    settings: Res<Settings>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    if !view
+        .moved
+        .is_some_and(|moved| moved.elapsed() >= SETTLE_TIME)
+    {
+        return;
+    }
+    view.moved = None;
+    let job = RenderJob::start(&scene.0, view.camera(), settings.0, &mut images);
+    commands.insert_resource(job);
+}
+
+/// Copies the tiles finished since the last frame into the image.
+fn receive_tiles(mut job: ResMut<RenderJob>, mut images: ResMut<Assets<Image>>) {
+    if job.preview || job.took.is_some() {
+        return;
+    }
+    let job = &mut *job;
+    if job.tasks.is_empty() {
+        job.took = Some(job.started.elapsed());
+    }
+    let mut finished = Vec::new();
+    job.tasks
+        .retain_mut(|task| match block_on(poll_once(task)) {
+            Some(result) => {
+                finished.push(result);
+                false
+            }
+            None => true,
+        });
+    if finished.is_empty() {
+        return;
+    }
+
+    let Some(data) = images
+        .get_mut(&job.image)
+        .and_then(|image| image.data.as_mut())
+    else {
+        return;
+    };
+    for (tile, colors) in finished {
+        for (i, color) in colors.into_iter().enumerate() {
+            let (x, y) = (tile.x + i % tile.width, tile.y + i / tile.width);
+            job.canvas.add_pixel(x, y, color);
+            if let Some(rgb) = job.canvas.get_display_pixel_u8(x, y) {
+                let offset = (y * job.canvas.width + x) * 4;
+                data[offset..offset + 3].copy_from_slice(&rgb);
+            }
+        }
+    }
+}
+
+/// Points the sprite at the image of the current render.
+fn show_image(job: Res<RenderJob>, mut sprites: Query<&mut Sprite, With<RenderView>>) {
+    if !job.is_changed() {
+        return;
+    }
+    for mut sprite in &mut sprites {
+        if sprite.image != job.image {
+            sprite.image = job.image.clone();
+        }
+    }
+}
+
+/// Shows the share of tiles traced, then how long the render took.
+fn show_progress(job: Res<RenderJob>, mut texts: Query<&mut Text, With<ProgressText>>) {
+    if !job.is_changed() {
+        return;
+    }
+    let done = job.tiles - job.tasks.len();
+    let progress = match (job.preview, job.took) {
+        (true, _) => "Preview".to_string(),
+        (false, Some(took)) => format!("Rendered in {:.1} s", took.as_secs_f64()),
+        (false, None) => format!("Rendering {}%", done * 100 / job.tiles.max(1)),
+    };
+    for mut text in &mut texts {
+        text.0.clone_from(&progress);
+    }
+}
+
+/// Scales the image to the largest size that fits the window, keeping the
+/// aspect ratio of the render.
+fn fit_to_window(
+    windows: Query<&Window, (With<PrimaryWindow>, Changed<Window>)>,
+    settings: Res<Settings>,
+    mut sprites: Query<&mut Sprite, With<RenderView>>,
+) {
+    let Some(window) = windows.iter().next() else {
+        return;
+    };
+    let image = Vec2::new(settings.0.width as f32, settings.0.height as f32);
+    let scale = (window.width() / image.x).min(window.height() / image.y);
+    for mut sprite in &mut sprites {
+        sprite.custom_size = Some(image * scale);
+    }
 }
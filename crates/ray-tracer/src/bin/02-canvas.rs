@@ -4,6 +4,7 @@ use ray_tracer::scene::Canvas;
 use ray_tracer::shape::Color;
 
 use std::fmt;
+use std::fs;
 
 struct DisplayTuple(Tuple);
 
@@ -89,5 +90,8 @@ fn main() {
         proj.tick(&env);
     }
     println!("Writing into file './renders/chapter01.ppm'");
-    let _ = canvas.to_ppm(Some("chapter01.ppm".to_owned()));
+    if let Err(e) = fs::create_dir_all("renders").and_then(|_| canvas.save("renders/chapter01.ppm"))
+    {
+        eprintln!("Failed to save image: {e}");
+    }
 }
@@ -1,4 +1,5 @@
 use std::f64::consts::PI;
+use std::fs;
 
 use ray_tracer::math::{Matrix, Tuple};
 use ray_tracer::scene::Canvas;
@@ -20,5 +21,8 @@ fn main() {
         canvas.add_pixel(x, y, color);
     }
     println!("Writing into file './renders/chapter04.ppm'");
-    let _ = canvas.to_ppm(Some("chapter04.ppm".to_owned()));
+    if let Err(e) = fs::create_dir_all("renders").and_then(|_| canvas.save("renders/chapter04.ppm"))
+    {
+        eprintln!("Failed to save image: {e}");
+    }
 }
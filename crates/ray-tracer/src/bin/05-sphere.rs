@@ -2,6 +2,7 @@ use ray_tracer::math::{Matrix, Ray, Tuple};
 use ray_tracer::scene::Canvas;
 use ray_tracer::shape::{Color, Sphere};
 use std::f64::consts::PI;
+use std::fs;
 
 fn main() {
     let ray_orig = Tuple::point(0.0, 0.0, -5.0);
@@ -42,5 +43,8 @@ fn main() {
     }
 
     println!("Writing into file './renders/chapter05.ppm'");
-    let _ = canvas.to_ppm(Some("chapter05.ppm".to_owned()));
+    if let Err(e) = fs::create_dir_all("renders").and_then(|_| canvas.save("renders/chapter05.ppm"))
+    {
+        eprintln!("Failed to save image: {e}");
+    }
 }
@@ -2,6 +2,7 @@ use ray_tracer::lighting::{Light, lighting};
 use ray_tracer::math::{Ray, Tuple};
 use ray_tracer::scene::Canvas;
 use ray_tracer::shape::{Color, Sphere};
+use std::fs;
 
 fn main() {
     let ray_orig = Tuple::point(0.0, 0.0, -5.0);
@@ -32,8 +33,8 @@ fn main() {
             let xs = sphere.intersect(ray);
 
             let hit = xs.iter().find(|item| **item >= 0.0);
-            if hit.is_some() {
-                let point = ray.position(*hit.unwrap());
+            if let Some(t) = hit {
+                let point = ray.position(*t);
                 let normal = sphere.normal_at(point);
                 let eye = -ray.direction;
                 let final_color = lighting(&sphere.material, &light, &point, &eye, &normal);
@@ -43,5 +44,8 @@ fn main() {
     }
 
     println!("Writing into file './renders/chapter05.ppm'");
-    let _ = canvas.to_ppm(Some("chapter05.ppm".to_owned()));
+    if let Err(e) = fs::create_dir_all("renders").and_then(|_| canvas.save("renders/chapter05.ppm"))
+    {
+        eprintln!("Failed to save image: {e}");
+    }
 }
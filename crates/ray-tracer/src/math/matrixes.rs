@@ -214,7 +214,7 @@ impl Matrix<4> {
     pub fn rotation_y(radians: f64) -> Self {
         let mut res: Matrix<4> = Matrix::<4>::identity();
         res[0][0] = radians.cos();
-        res[0][3] = radians.sin();
+        res[0][2] = radians.sin();
         res[2][0] = -radians.sin();
         res[2][2] = radians.cos();
         res
@@ -726,6 +726,11 @@ mod tests {
             Tuple::point(2.0_f64.sqrt() / 2.0, 0.0, 2.0_f64.sqrt() / 2.0)
         );
         assert_eq!(full_res, Tuple::point(1.0, 0.0, 0.0));
+
+        let v = Tuple::vector(0.0, 0.0, 1.0);
+        assert_eq!(full_quarter * v, Tuple::vector(1.0, 0.0, 0.0));
+        let p = Tuple::point(1.0, 0.0, 0.0);
+        assert_eq!(full_quarter * p, Tuple::point(0.0, 0.0, -1.0));
     }
 
     #[test]
@@ -1,4 +1,5 @@
-use crate::math::{Matrix, Tuple};
+use crate::math::{Matrix, Ray, Tuple};
+use crate::scene::{Canvas, RenderSettings, World};
 
 #[derive(Debug, PartialEq)]
 pub struct Camera {
@@ -25,6 +26,10 @@ impl Camera {
         self.position
     }
 
+    pub fn get_target(&self) -> Tuple {
+        self.target
+    }
+
     pub fn get_forward(&self) -> Tuple {
         (self.target - self.position).normalize()
     }
@@ -38,9 +43,48 @@ impl Camera {
     }
 }
 
+impl Camera {
+    /// Returns the ray from the camera through the center of pixel `(x, y)`
+    /// of a `width` by `height` image. `fov` is the vertical field of view,
+    /// in degrees.
+    pub fn ray_for_pixel(&self, x: usize, y: usize, width: usize, height: usize) -> Ray {
+        self.ray_through(x as f64 + 0.5, y as f64 + 0.5, width, height)
+    }
+
+    /// Returns the ray from the camera through the point `(x, y)` of a
+    /// `width` by `height` image, measured in pixels from its top left
+    /// corner.
+    pub fn ray_through(&self, x: f64, y: f64, width: usize, height: usize) -> Ray {
+        let half_height = (self.fov.to_radians() / 2.0).tan();
+        let half_width = half_height * width as f64 / height as f64;
+        let pixel_size = 2.0 * half_height / height as f64;
+
+        // the camera looks down -z, so +x is to the left of the image
+        let world_x = half_width - x * pixel_size;
+        let world_y = half_height - y * pixel_size;
+
+        let pixel = self.inverse_view_transform * Tuple::point(world_x, world_y, -1.0);
+        let origin = self.inverse_view_transform * Tuple::point(0.0, 0.0, 0.0);
+        Ray::new(origin, (pixel - origin).normalize())
+    }
+
+    /// Renders `world` into a new `width` by `height` canvas, with one ray
+    /// through the center of each pixel.
+    pub fn render(&self, world: &World, width: usize, height: usize) -> Canvas {
+        let settings = RenderSettings {
+            width,
+            height,
+            threads: 1,
+            ..RenderSettings::default()
+        };
+        self.render_with(world, &settings)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::shape::Color;
 
     #[test]
     fn create_new_camera_ok() {
@@ -58,4 +102,35 @@ mod tests {
         };
         assert_eq!(camera, expected);
     }
+
+    #[test]
+    fn ray_for_pixel_ok() {
+        let camera = Camera::new(
+            Tuple::point(0.0, 0.0, 0.0),
+            Tuple::point(0.0, 0.0, -1.0),
+            90.0,
+        );
+        let ray = camera.ray_for_pixel(100, 50, 201, 101);
+        assert_eq!(ray.origin, Tuple::point(0.0, 0.0, 0.0));
+        assert_eq!(ray.direction, Tuple::vector(0.0, 0.0, -1.0));
+
+        let ray = camera.ray_for_pixel(0, 0, 201, 101);
+        assert_eq!(ray.direction, Tuple::vector(0.81513, 0.40757, -0.41164));
+    }
+
+    #[test]
+    fn render_ok() {
+        let world = World::new();
+        let camera = Camera::new(
+            Tuple::point(0.0, 0.0, -5.0),
+            Tuple::point(0.0, 0.0, 0.0),
+            90.0,
+        );
+        let canvas = camera.render(&world, 11, 11);
+        assert_eq!(
+            canvas.get_pixel(5, 5),
+            Some(Color::new(0.38066, 0.47583, 0.2855))
+        );
+        assert_eq!(canvas.get_pixel(0, 0), Some(Color::new_black()));
+    }
 }
@@ -0,0 +1,197 @@
+//! # Animated Image Module
+//!
+//! Encodes a sequence of equally sized canvases as an animated image that
+//! loops forever, to preview rendered frames without external tools.
+//!
+//! ## Features
+//! - Animated GIF with a shared palette and optional dithering.
+//! - Animated PNG (APNG) in full 8-bit color.
+//! - Select the format by file extension when saving.
+
+use crate::scene::Canvas;
+use crate::scene::canvas::{gif, png};
+use std::{
+    fs,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+/// An encoding of animated images.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnimationFormat {
+    /// GIF, reduced to a palette of at most 256 colors.
+    Gif,
+    /// Animated PNG, in 8-bit RGB.
+    Apng,
+}
+
+impl AnimationFormat {
+    /// Selects a format from a file extension (case insensitive): `gif`,
+    /// or `png` and `apng` for APNG.
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        match extension.to_ascii_lowercase().as_str() {
+            "gif" => Some(AnimationFormat::Gif),
+            "png" | "apng" => Some(AnimationFormat::Apng),
+            _ => None,
+        }
+    }
+
+    /// Selects a format from the extension of `path`.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Option<Self> {
+        path.as_ref()
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .and_then(AnimationFormat::from_extension)
+    }
+}
+
+/// How an animation is played back and encoded.
+///
+/// ## Fields
+/// - `fps`: The number of frames shown per second. GIF stores delays in
+///   hundredths of a second, so its rate is rounded.
+/// - `dither`: Whether GIF colors are dithered when reduced to a palette.
+///   APNG keeps full color and ignores it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AnimationOptions {
+    pub fps: f64,
+    pub dither: bool,
+}
+
+impl Default for AnimationOptions {
+    /// 24 frames per second, dithered.
+    fn default() -> Self {
+        Self {
+            fps: 24.0,
+            dither: true,
+        }
+    }
+}
+
+/// Writes `frames` to `writer` as an animated image in the given format.
+///
+/// Frames are converted with their own display transform, like single
+/// images.
+///
+/// ## Returns
+/// An error of kind `InvalidInput` if there are no frames, if their sizes
+/// differ or if `fps` is not positive, or the error raised while writing.
+pub fn write_animation<W: Write>(
+    frames: &[Canvas],
+    writer: &mut W,
+    format: AnimationFormat,
+    options: AnimationOptions,
+) -> Result<(), io::Error> {
+    if !(options.fps > 0.0 && options.fps.is_finite()) {
+        return Err(invalid("the frame rate must be positive"));
+    }
+    if let Some(first) = frames.first()
+        && frames
+            .iter()
+            .any(|frame| (frame.width, frame.height) != (first.width, first.height))
+    {
+        return Err(invalid("all frames must have the same size"));
+    }
+    match format {
+        AnimationFormat::Gif => {
+            let delay = (100.0 / options.fps).round().clamp(1.0, u16::MAX as f64) as u16;
+            gif::write_gif(frames, delay, options.dither, writer)
+        }
+        AnimationFormat::Apng => {
+            let denominator = (options.fps * 100.0).round().clamp(1.0, u16::MAX as f64) as u16;
+            png::write_apng(frames, (100, denominator), writer)
+        }
+    }
+}
+
+/// Saves `frames` to exactly `path` as an animated image, choosing the
+/// format from its extension.
+///
+/// Parent directories are not created.
+///
+/// ## Returns
+/// An error of kind `Unsupported` if the extension is unknown, or any error
+/// of `write_animation`.
+pub fn save_animation<P: AsRef<Path>>(
+    frames: &[Canvas],
+    path: P,
+    options: AnimationOptions,
+) -> Result<(), io::Error> {
+    let format = AnimationFormat::from_path(&path).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::Unsupported,
+            "Error: unsupported animation file extension.",
+        )
+    })?;
+    let mut f = BufWriter::new(fs::File::create(path)?);
+    write_animation(frames, &mut f, format, options)?;
+    f.flush()
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, format!("Error: {message}."))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shape::Color;
+
+    fn frames() -> Vec<Canvas> {
+        [Color::new(1.0, 0.0, 0.0), Color::new(0.0, 0.0, 1.0)]
+            .into_iter()
+            .map(|color| Canvas::new_with_color(3, 2, color))
+            .collect()
+    }
+
+    #[test]
+    fn test_from_path() {
+        assert_eq!(
+            AnimationFormat::from_path("out/turntable.GIF"),
+            Some(AnimationFormat::Gif)
+        );
+        assert_eq!(
+            AnimationFormat::from_path("out/turntable.apng"),
+            Some(AnimationFormat::Apng)
+        );
+        assert_eq!(
+            AnimationFormat::from_path("out/turntable.png"),
+            Some(AnimationFormat::Apng)
+        );
+        assert_eq!(AnimationFormat::from_path("out/turntable.ppm"), None);
+    }
+
+    #[test]
+    fn test_save_animation() {
+        let dir = tempfile::tempdir().unwrap();
+        let options = AnimationOptions::default();
+        for name in ["turntable.gif", "turntable.png"] {
+            let path = dir.path().join(name);
+            save_animation(&frames(), &path, options).unwrap();
+            assert!(fs::metadata(&path).unwrap().len() > 0);
+        }
+        // the first APNG frame is also the still image
+        let still = Canvas::load(dir.path().join("turntable.png")).unwrap();
+        assert_eq!(still.get_pixel(0, 0), Some(Color::new(1.0, 0.0, 0.0)));
+
+        let err = save_animation(&frames(), dir.path().join("turntable.bmp"), options);
+        assert_eq!(err.unwrap_err().kind(), io::ErrorKind::Unsupported);
+    }
+
+    #[test]
+    fn test_write_animation_yields_error() {
+        let mut out: Vec<u8> = Vec::new();
+        let mut frames = frames();
+        let options = AnimationOptions {
+            fps: 0.0,
+            ..Default::default()
+        };
+        let err = write_animation(&frames, &mut out, AnimationFormat::Gif, options);
+        assert_eq!(err.unwrap_err().kind(), io::ErrorKind::InvalidInput);
+
+        frames.push(Canvas::new(2, 2));
+        let options = AnimationOptions::default();
+        let err = write_animation(&frames, &mut out, AnimationFormat::Apng, options);
+        assert_eq!(err.unwrap_err().kind(), io::ErrorKind::InvalidInput);
+    }
+}
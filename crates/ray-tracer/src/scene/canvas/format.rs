@@ -0,0 +1,101 @@
+//! # Image Format Module
+//!
+//! Describes the image formats a `Canvas` can be encoded to or decoded from
+//! and maps file extensions onto them.
+
+use crate::scene::canvas::{PngFormat, PpmFormat};
+use std::path::Path;
+
+/// An image encoding supported by `Canvas::save_as` and `Canvas::write_to`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFormat {
+    /// Portable Pixmap in the given variant.
+    Ppm(PpmFormat),
+    /// Portable Network Graphics with the given pixel layout.
+    Png(PngFormat),
+    /// Portable Float Map with unclamped 32-bit float channels.
+    Pfm,
+    /// Radiance RGBE high-dynamic-range image.
+    Hdr,
+}
+
+impl ImageFormat {
+    /// Selects a format from a file extension (case insensitive).
+    ///
+    /// `ppm` maps to binary (`P6`) PPM, `png` to 8-bit RGB PNG, `pfm` to
+    /// Portable Float Map and `hdr` to Radiance RGBE.
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        match extension.to_ascii_lowercase().as_str() {
+            "ppm" => Some(ImageFormat::Ppm(PpmFormat::Binary)),
+            "png" => Some(ImageFormat::Png(PngFormat::Rgb8)),
+            "pfm" => Some(ImageFormat::Pfm),
+            "hdr" => Some(ImageFormat::Hdr),
+            _ => None,
+        }
+    }
+
+    /// Returns the usual file extension of the format.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ImageFormat::Ppm(_) => "ppm",
+            ImageFormat::Png(_) => "png",
+            ImageFormat::Pfm => "pfm",
+            ImageFormat::Hdr => "hdr",
+        }
+    }
+
+    /// Selects a format from the extension of `path`.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Option<Self> {
+        path.as_ref()
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .and_then(ImageFormat::from_extension)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_extension() {
+        assert_eq!(
+            ImageFormat::from_extension("ppm"),
+            Some(ImageFormat::Ppm(PpmFormat::Binary))
+        );
+        assert_eq!(
+            ImageFormat::from_extension("PPM"),
+            Some(ImageFormat::Ppm(PpmFormat::Binary))
+        );
+        assert_eq!(
+            ImageFormat::from_extension("png"),
+            Some(ImageFormat::Png(PngFormat::Rgb8))
+        );
+        assert_eq!(ImageFormat::from_extension("pfm"), Some(ImageFormat::Pfm));
+        assert_eq!(ImageFormat::from_extension("hdr"), Some(ImageFormat::Hdr));
+        assert_eq!(ImageFormat::from_extension("bmp"), None);
+    }
+
+    #[test]
+    fn test_extension() {
+        for format in [
+            ImageFormat::Ppm(PpmFormat::Ascii),
+            ImageFormat::Png(PngFormat::Rgb16),
+            ImageFormat::Pfm,
+            ImageFormat::Hdr,
+        ] {
+            let format_back = ImageFormat::from_extension(format.extension()).unwrap();
+            assert_eq!(format_back.extension(), format.extension());
+        }
+    }
+
+    #[test]
+    fn test_from_path() {
+        assert_eq!(
+            ImageFormat::from_path("/tmp/out/frame.ppm"),
+            Some(ImageFormat::Ppm(PpmFormat::Binary))
+        );
+        assert_eq!(ImageFormat::from_path("renders/frame"), None);
+        assert_eq!(ImageFormat::from_path("renders/.ppm"), None);
+    }
+}
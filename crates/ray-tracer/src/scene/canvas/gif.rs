@@ -0,0 +1,302 @@
+//! # GIF Module
+//!
+//! Encoding of animated GIF images using the `gif` crate.
+//!
+//! ## Features
+//! - Reduce the colors of all frames to one shared palette of at most 256
+//!   colors by median cut, so that colors do not flicker between frames.
+//! - Optionally spread the rounding error with Floyd–Steinberg dithering.
+
+use crate::scene::Canvas;
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+/// The largest palette a GIF image can hold.
+const MAX_COLORS: usize = 256;
+
+/// Writes equally sized frames as a looping animated GIF, each shown for
+/// `delay` hundredths of a second.
+pub(crate) fn write_gif<W: Write>(
+    frames: &[Canvas],
+    delay: u16,
+    dither: bool,
+    writer: &mut W,
+) -> io::Result<()> {
+    let first = frames.first().ok_or_else(no_frames)?;
+    let width = u16::try_from(first.width).map_err(|_| too_large())?;
+    let height = u16::try_from(first.height).map_err(|_| too_large())?;
+
+    let images: Vec<Vec<[u8; 3]>> = frames.iter().map(display_pixels).collect();
+    let palette = median_cut(&histogram(&images), MAX_COLORS);
+    let flat: Vec<u8> = palette.iter().flatten().copied().collect();
+
+    let mut encoder =
+        ::gif::Encoder::new(writer, width, height, &flat).map_err(io::Error::other)?;
+    encoder
+        .set_repeat(::gif::Repeat::Infinite)
+        .map_err(io::Error::other)?;
+    for image in &images {
+        let indices = match dither {
+            true => dither_indices(image, first.width, &palette),
+            false => nearest_indices(image, &palette),
+        };
+        let mut frame = ::gif::Frame::from_indexed_pixels(width, height, indices, None);
+        frame.delay = delay;
+        encoder.write_frame(&frame).map_err(io::Error::other)?;
+    }
+    Ok(())
+}
+
+/// Converts the canvas pixels to 8-bit display values in row-major order.
+fn display_pixels(canvas: &Canvas) -> Vec<[u8; 3]> {
+    canvas
+        .pixels
+        .iter()
+        .enumerate()
+        .map(|(i, px)| {
+            let width = canvas.width.max(1);
+            canvas.display.to_u8(*px, i % width, i / width)
+        })
+        .collect()
+}
+
+/// Counts how often each color appears across all images.
+fn histogram(images: &[Vec<[u8; 3]>]) -> Vec<([u8; 3], u64)> {
+    let mut counts: HashMap<[u8; 3], u64> = HashMap::new();
+    for color in images.iter().flatten() {
+        *counts.entry(*color).or_insert(0) += 1;
+    }
+    let mut colors: Vec<([u8; 3], u64)> = counts.into_iter().collect();
+    // sort for a palette that does not depend on the hash order
+    colors.sort_unstable();
+    colors
+}
+
+/// Builds a palette of at most `size` colors by median cut.
+///
+/// The colors start in a single box. The box with the widest channel range
+/// is repeatedly split at the weighted median of that channel, until there
+/// are `size` boxes or no box can be split. Each box contributes the
+/// weighted average of its colors.
+fn median_cut(colors: &[([u8; 3], u64)], size: usize) -> Vec<[u8; 3]> {
+    if colors.is_empty() {
+        return vec![[0, 0, 0]];
+    }
+    let mut boxes: Vec<Vec<([u8; 3], u64)>> = vec![colors.to_vec()];
+    while boxes.len() < size {
+        let widest = boxes
+            .iter()
+            .enumerate()
+            .filter(|(_, colors)| colors.len() > 1)
+            .map(|(i, colors)| (i, widest_channel(colors)))
+            .max_by_key(|&(_, (_, range))| range);
+        let Some((i, (channel, _))) = widest else {
+            break;
+        };
+        let mut colors = boxes.swap_remove(i);
+        colors.sort_unstable_by_key(|(color, _)| color[channel]);
+        let total: u64 = colors.iter().map(|(_, count)| count).sum();
+        let mut seen = 0;
+        let median = colors
+            .iter()
+            .position(|(_, count)| {
+                seen += count;
+                seen * 2 >= total
+            })
+            .unwrap_or(0);
+        // both halves keep at least one color
+        let upper = colors.split_off((median + 1).min(colors.len() - 1));
+        boxes.push(colors);
+        boxes.push(upper);
+    }
+    boxes.iter().map(|colors| average(colors)).collect()
+}
+
+/// Returns the channel with the widest range of values, and that range.
+fn widest_channel(colors: &[([u8; 3], u64)]) -> (usize, u8) {
+    (0..3)
+        .map(|channel| {
+            let values = colors.iter().map(|(color, _)| color[channel]);
+            let min = values.clone().min().unwrap_or(0);
+            let max = values.max().unwrap_or(0);
+            (channel, max - min)
+        })
+        .max_by_key(|&(_, range)| range)
+        .unwrap_or((0, 0))
+}
+
+fn average(colors: &[([u8; 3], u64)]) -> [u8; 3] {
+    let total: u64 = colors.iter().map(|(_, count)| count).sum::<u64>().max(1);
+    let mut sum = [0u64; 3];
+    for (color, count) in colors {
+        for channel in 0..3 {
+            sum[channel] += color[channel] as u64 * count;
+        }
+    }
+    sum.map(|s| ((s + total / 2) / total) as u8)
+}
+
+/// Maps every pixel to the index of the closest palette color.
+fn nearest_indices(image: &[[u8; 3]], palette: &[[u8; 3]]) -> Vec<u8> {
+    let mut cache: HashMap<[u8; 3], u8> = HashMap::new();
+    image
+        .iter()
+        .map(|color| {
+            *cache
+                .entry(*color)
+                .or_insert_with(|| nearest(color.map(|c| c as f32), palette))
+        })
+        .collect()
+}
+
+/// Maps every pixel to a palette index with Floyd–Steinberg dithering: the
+/// difference between a pixel and its palette color is passed on to the
+/// neighbours that are not mapped yet.
+fn dither_indices(image: &[[u8; 3]], width: usize, palette: &[[u8; 3]]) -> Vec<u8> {
+    let width = width.max(1);
+    let mut errors = vec![[0.0f32; 3]; image.len()];
+    let mut indices = Vec::with_capacity(image.len());
+    for (i, color) in image.iter().enumerate() {
+        let (x, y) = (i % width, i / width);
+        let mut wanted = [0.0f32; 3];
+        for channel in 0..3 {
+            wanted[channel] = (color[channel] as f32 + errors[i][channel]).clamp(0.0, 255.0);
+        }
+        let index = nearest(wanted, palette);
+        indices.push(index);
+
+        let chosen = palette[index as usize];
+        let mut spread = |dx: isize, dy: usize, weight: f32| {
+            let nx = x as isize + dx;
+            if nx < 0 || nx as usize >= width {
+                return;
+            }
+            let j = (y + dy) * width + nx as usize;
+            if let Some(error) = errors.get_mut(j) {
+                for channel in 0..3 {
+                    error[channel] += (wanted[channel] - chosen[channel] as f32) * weight;
+                }
+            }
+        };
+        spread(1, 0, 7.0 / 16.0);
+        spread(-1, 1, 3.0 / 16.0);
+        spread(0, 1, 5.0 / 16.0);
+        spread(1, 1, 1.0 / 16.0);
+    }
+    indices
+}
+
+/// Returns the index of the palette color closest to `color`.
+fn nearest(color: [f32; 3], palette: &[[u8; 3]]) -> u8 {
+    let distance = |entry: &[u8; 3]| -> f32 {
+        (0..3)
+            .map(|channel| (color[channel] - entry[channel] as f32).powi(2))
+            .sum()
+    };
+    palette
+        .iter()
+        .enumerate()
+        .min_by(|(_, a), (_, b)| distance(a).total_cmp(&distance(b)))
+        .map_or(0, |(i, _)| i as u8)
+}
+
+fn no_frames() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        "GIF: an animation needs at least one frame.",
+    )
+}
+
+fn too_large() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        "GIF: image dimensions are too large.",
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shape::Color;
+
+    fn gradient(width: usize, shift: f64) -> Canvas {
+        let mut canvas = Canvas::new(width, 2);
+        for x in 0..width {
+            let v = (x as f64 / (width - 1) as f64 + shift).min(1.0);
+            canvas.add_pixel(x, 0, Color::new(v, 0.0, 1.0 - v));
+            canvas.add_pixel(x, 1, Color::new(0.0, v, 0.0));
+        }
+        canvas
+    }
+
+    fn decode(data: &[u8]) -> Vec<::gif::Frame<'static>> {
+        let mut options = ::gif::DecodeOptions::new();
+        options.set_color_output(::gif::ColorOutput::Indexed);
+        let mut decoder = options.read_info(data).unwrap();
+        let mut frames = Vec::new();
+        while let Some(frame) = decoder.read_next_frame().unwrap() {
+            frames.push(frame.clone());
+        }
+        frames
+    }
+
+    #[test]
+    fn test_write_gif() {
+        let frames = [gradient(4, 0.0), gradient(4, 0.5)];
+        let mut out: Vec<u8> = Vec::new();
+        write_gif(&frames, 4, false, &mut out).unwrap();
+        assert!(out.starts_with(b"GIF89a"));
+
+        let decoded = decode(&out);
+        assert_eq!(decoded.len(), 2);
+        assert_eq!(decoded[1].delay, 4);
+        assert_eq!((decoded[0].width, decoded[0].height), (4, 2));
+
+        // few colors fit the palette exactly
+        let decoder = ::gif::DecodeOptions::new()
+            .read_info(out.as_slice())
+            .unwrap();
+        let palette = decoder.global_palette().unwrap();
+        let color = |frame: &::gif::Frame, i: usize| {
+            let index = frame.buffer[i] as usize * 3;
+            [palette[index], palette[index + 1], palette[index + 2]]
+        };
+        assert_eq!(color(&decoded[0], 0), [0, 0, 255]);
+        assert_eq!(color(&decoded[0], 3), [255, 0, 0]);
+        assert_eq!(color(&decoded[1], 7), [0, 255, 0]);
+    }
+
+    #[test]
+    fn test_median_cut() {
+        let colors = [([0, 0, 0], 1), ([10, 0, 0], 1), ([200, 0, 0], 2)];
+        let sorted = |size: usize| {
+            let mut palette = median_cut(&colors, size);
+            palette.sort_unstable();
+            palette
+        };
+        assert_eq!(sorted(4), [[0, 0, 0], [10, 0, 0], [200, 0, 0]]);
+        assert_eq!(sorted(2), [[5, 0, 0], [200, 0, 0]]);
+        assert_eq!(sorted(1), [[103, 0, 0]]);
+        assert_eq!(median_cut(&[], 4), [[0, 0, 0]]);
+    }
+
+    #[test]
+    fn test_dither_indices() {
+        // a mid gray between black and white dithers to an even mix
+        let image = vec![[128, 128, 128]; 16];
+        let palette = [[0, 0, 0], [255, 255, 255]];
+        assert_eq!(nearest_indices(&image, &palette), vec![1; 16]);
+
+        let indices = dither_indices(&image, 4, &palette);
+        let white = indices.iter().filter(|&&i| i == 1).count();
+        assert!((7..=9).contains(&white), "{indices:?}");
+    }
+
+    #[test]
+    fn test_write_gif_yields_error() {
+        let err = write_gif(&[], 4, false, &mut Vec::new()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        let err = write_gif(&[Canvas::new(70_000, 1)], 4, false, &mut Vec::new()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+}
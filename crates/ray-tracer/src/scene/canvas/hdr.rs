@@ -0,0 +1,375 @@
+//! # HDR Module
+//!
+//! Encoding and decoding of Radiance `.hdr` (RGBE) images. Each pixel is
+//! stored as three 8-bit mantissas sharing an 8-bit exponent, which keeps
+//! about 1% relative precision over a very large dynamic range.
+//!
+//! ## Features
+//! - Write a canvas as an uncompressed `32-bit_rle_rgbe` image.
+//! - Read flat, run-length encoded and old-style run-length encoded images.
+
+use crate::scene::Canvas;
+use crate::scene::canvas::ppm::invalid_data;
+use crate::shape::Color;
+use std::io::{self, Read, Write};
+
+const HDR_MAGIC: &str = "#?RADIANCE";
+const HDR_MAGIC_ALT: &str = "#?RGBE";
+const HDR_FORMAT: &str = "FORMAT=32-bit_rle_rgbe";
+/// Scanlines shorter or longer than this cannot use the new RLE scheme.
+const HDR_RLE_MIN_WIDTH: usize = 8;
+const HDR_RLE_MAX_WIDTH: usize = 0x7fff;
+/// The largest image read, in pixels, so that a corrupt header cannot ask
+/// for an absurd amount of memory.
+const HDR_MAX_PIXELS: usize = 1 << 27;
+
+/// Writes the canvas as a Radiance HDR image.
+///
+/// Negative channel values cannot be represented and are written as zero.
+pub(crate) fn write_hdr<W: Write>(canvas: &Canvas, writer: &mut W) -> io::Result<()> {
+    writeln!(writer, "{}", HDR_MAGIC)?;
+    writeln!(writer, "{}", HDR_FORMAT)?;
+    writeln!(writer)?;
+    writeln!(writer, "-Y {} +X {}", canvas.height, canvas.width)?;
+
+    let mut row_bytes: Vec<u8> = Vec::with_capacity(canvas.width * 4);
+    for row in canvas.pixels.chunks(canvas.width.max(1)) {
+        row_bytes.clear();
+        for px in row {
+            row_bytes.extend_from_slice(&to_rgbe(px));
+        }
+        writer.write_all(&row_bytes)?;
+    }
+    Ok(())
+}
+
+/// Converts a color into shared-exponent RGBE bytes.
+fn to_rgbe(px: &Color) -> [u8; 4] {
+    let r = px.get_red().max(0.0);
+    let g = px.get_green().max(0.0);
+    let b = px.get_blue().max(0.0);
+    let v = r.max(g).max(b);
+    if v < 1e-32 || !v.is_finite() {
+        return [0, 0, 0, 0];
+    }
+    // v = m * 2^e with m in [0.5, 1)
+    let mut e = v.log2().floor() as i32 + 1;
+    if v / 2f64.powi(e) >= 1.0 {
+        e += 1;
+    } else if v / 2f64.powi(e) < 0.5 {
+        e -= 1;
+    }
+    let e = e.clamp(-128, 127);
+    let scale = 256.0 / 2f64.powi(e);
+    [
+        (r * scale).min(255.0) as u8,
+        (g * scale).min(255.0) as u8,
+        (b * scale).min(255.0) as u8,
+        (e + 128) as u8,
+    ]
+}
+
+/// Converts shared-exponent RGBE bytes into a color.
+fn from_rgbe(rgbe: &[u8]) -> Color {
+    if rgbe[3] == 0 {
+        return Color::new_black();
+    }
+    let f = 2f64.powi(rgbe[3] as i32 - (128 + 8));
+    Color::new(rgbe[0] as f64 * f, rgbe[1] as f64 * f, rgbe[2] as f64 * f)
+}
+
+/// Reads a Radiance HDR image into a new canvas.
+///
+/// Only the standard `-Y <height> +X <width>` orientation is supported.
+pub(crate) fn read_hdr<R: Read>(mut reader: R) -> io::Result<Canvas> {
+    let mut data: Vec<u8> = Vec::new();
+    reader.read_to_end(&mut data)?;
+
+    let mut lines = HeaderLines {
+        data: &data,
+        pos: 0,
+    };
+    let magic = lines.next_line()?;
+    if magic != HDR_MAGIC && magic != HDR_MAGIC_ALT {
+        return Err(invalid_data("HDR: unsupported magic number."));
+    }
+    // header variables end with an empty line
+    loop {
+        let line = lines.next_line()?;
+        if line.is_empty() {
+            break;
+        }
+        if let Some(format) = line.strip_prefix("FORMAT=")
+            && format != "32-bit_rle_rgbe"
+        {
+            return Err(invalid_data("HDR: unsupported pixel format."));
+        }
+    }
+
+    let resolution: Vec<&str> = lines.next_line()?.split_whitespace().collect();
+    let (height, width) = match resolution.as_slice() {
+        ["-Y", h, "+X", w] => (
+            h.parse::<usize>()
+                .map_err(|_| invalid_data("HDR: invalid image height."))?,
+            w.parse::<usize>()
+                .map_err(|_| invalid_data("HDR: invalid image width."))?,
+        ),
+        _ => return Err(invalid_data("HDR: unsupported image orientation.")),
+    };
+
+    // each side is bounded too, as an empty image can have a huge other side
+    if width > HDR_MAX_PIXELS
+        || height > HDR_MAX_PIXELS
+        || width
+            .checked_mul(height)
+            .is_none_or(|count| count > HDR_MAX_PIXELS)
+    {
+        return Err(invalid_data("HDR: image dimensions are too large."));
+    }
+    let scanline_len = width
+        .checked_mul(4)
+        .ok_or_else(|| invalid_data("HDR: image dimensions are too large."))?;
+
+    let mut scanlines = Scanlines {
+        data: &data,
+        pos: lines.pos,
+    };
+    // grown as scanlines are decoded, so truncated files stop early
+    let mut pixels: Vec<Color> = Vec::new();
+    let mut scanline: Vec<u8> = vec![0; scanline_len];
+    for _ in 0..height {
+        scanlines.read(&mut scanline, width)?;
+        pixels.extend(scanline.chunks_exact(4).map(from_rgbe));
+    }
+
+    Ok(Canvas::from_pixels(width, height, pixels))
+}
+
+/// Splits the text header into newline terminated lines.
+struct HeaderLines<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> HeaderLines<'a> {
+    fn next_line(&mut self) -> io::Result<&'a str> {
+        let rest = &self.data[self.pos..];
+        let end = rest
+            .iter()
+            .position(|b| *b == b'\n')
+            .ok_or_else(|| invalid_data("Error: unexpected end of image header."))?;
+        self.pos += end + 1;
+        std::str::from_utf8(&rest[..end])
+            .map(str::trim_end)
+            .map_err(|_| invalid_data("HDR: header is not valid text."))
+    }
+}
+
+/// Decodes scanlines in flat, RLE or old-style RLE layout.
+struct Scanlines<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl Scanlines<'_> {
+    fn byte(&mut self) -> io::Result<u8> {
+        let b = *self
+            .data
+            .get(self.pos)
+            .ok_or_else(|| invalid_data("HDR: unexpected end of raster data."))?;
+        self.pos += 1;
+        Ok(b)
+    }
+
+    fn read(&mut self, out: &mut [u8], width: usize) -> io::Result<()> {
+        let head = self.data.get(self.pos..self.pos + 4);
+        match head {
+            Some([2, 2, hi, lo]) if (HDR_RLE_MIN_WIDTH..=HDR_RLE_MAX_WIDTH).contains(&width) => {
+                if ((*hi as usize) << 8 | *lo as usize) != width {
+                    return Err(invalid_data("HDR: scanline width mismatch."));
+                }
+                self.pos += 4;
+                self.read_rle(out, width)
+            }
+            _ => self.read_flat(out, width),
+        }
+    }
+
+    /// New-style RLE: each of the four components is encoded separately.
+    fn read_rle(&mut self, out: &mut [u8], width: usize) -> io::Result<()> {
+        for component in 0..4 {
+            let mut x = 0;
+            while x < width {
+                let count = self.byte()? as usize;
+                if count > 128 {
+                    let count = count - 128;
+                    if x + count > width {
+                        return Err(invalid_data("HDR: run exceeds scanline."));
+                    }
+                    let value = self.byte()?;
+                    for i in x..x + count {
+                        out[i * 4 + component] = value;
+                    }
+                    x += count;
+                } else {
+                    if count == 0 || x + count > width {
+                        return Err(invalid_data("HDR: invalid run length."));
+                    }
+                    for i in x..x + count {
+                        out[i * 4 + component] = self.byte()?;
+                    }
+                    x += count;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Flat pixels, possibly with old-style `1 1 1 n` repeat markers.
+    fn read_flat(&mut self, out: &mut [u8], width: usize) -> io::Result<()> {
+        let mut x = 0;
+        let mut shift = 0;
+        while x < width {
+            let px = [self.byte()?, self.byte()?, self.byte()?, self.byte()?];
+            if px[..3] == [1, 1, 1] {
+                if x == 0 {
+                    return Err(invalid_data("HDR: repeat marker without a pixel."));
+                }
+                // consecutive markers hold ever higher bytes of the count
+                let end = 1usize
+                    .checked_shl(shift)
+                    .and_then(|scale| (px[3] as usize).checked_mul(scale))
+                    .and_then(|count| x.checked_add(count))
+                    .filter(|end| *end <= width)
+                    .ok_or_else(|| invalid_data("HDR: run exceeds scanline."))?;
+                let count = end - x;
+                let prev = [
+                    out[x * 4 - 4],
+                    out[x * 4 - 3],
+                    out[x * 4 - 2],
+                    out[x * 4 - 1],
+                ];
+                for i in x..x + count {
+                    out[i * 4..i * 4 + 4].copy_from_slice(&prev);
+                }
+                x += count;
+                shift = shift.saturating_add(8);
+            } else {
+                out[x * 4..x * 4 + 4].copy_from_slice(&px);
+                x += 1;
+                shift = 0;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// RGBE keeps about 1% precision relative to the brightest channel.
+    fn assert_close(a: Color, b: Color) {
+        let max = b.get_red().max(b.get_green()).max(b.get_blue());
+        for (x, y) in [
+            (a.get_red(), b.get_red()),
+            (a.get_green(), b.get_green()),
+            (a.get_blue(), b.get_blue()),
+        ] {
+            assert!((x - y).abs() <= max / 100.0, "{} != {}", a, b);
+        }
+    }
+
+    #[test]
+    fn test_rgbe_conversion() {
+        assert_eq!(to_rgbe(&Color::new(1.0, 0.5, 0.0)), [128, 64, 0, 129]);
+        assert_eq!(to_rgbe(&Color::new_black()), [0, 0, 0, 0]);
+        assert_eq!(to_rgbe(&Color::new(-1.0, 0.0, 0.0)), [0, 0, 0, 0]);
+        assert_eq!(from_rgbe(&[128, 64, 0, 129]), Color::new(1.0, 0.5, 0.0));
+        assert_eq!(from_rgbe(&[0, 0, 0, 0]), Color::new_black());
+        assert_close(
+            from_rgbe(&to_rgbe(&Color::new(123.4, 0.3, 7.0))),
+            Color::new(123.4, 0.3, 7.0),
+        );
+    }
+
+    #[test]
+    fn test_write_hdr_header() {
+        let canvas = Canvas::new(3, 2);
+        let mut out: Vec<u8> = Vec::new();
+        write_hdr(&canvas, &mut out).unwrap();
+        let header = b"#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y 2 +X 3\n";
+        assert!(out.starts_with(header));
+        assert_eq!(out.len(), header.len() + 3 * 2 * 4);
+    }
+
+    #[test]
+    fn test_hdr_round_trip() {
+        let mut canvas = Canvas::new(10, 3);
+        canvas.add_pixel(0, 0, Color::new(1.0, 0.5, 0.25));
+        canvas.add_pixel(9, 2, Color::new(40.0, 2.5, 0.01));
+        let mut out: Vec<u8> = Vec::new();
+        write_hdr(&canvas, &mut out).unwrap();
+
+        let res = read_hdr(out.as_slice()).unwrap();
+        assert_eq!(res.width, 10);
+        assert_eq!(res.height, 3);
+        assert_eq!(res.get_pixel(0, 0), Some(Color::new(1.0, 0.5, 0.25)));
+        assert_eq!(res.get_pixel(5, 1), Some(Color::new_black()));
+        assert_close(res.get_pixel(9, 2).unwrap(), Color::new(40.0, 2.5, 0.0));
+    }
+
+    #[test]
+    fn test_read_hdr_rle() {
+        let mut input = b"#?RGBE\n# comment\nFORMAT=32-bit_rle_rgbe\n\n-Y 1 +X 8\n".to_vec();
+        input.extend_from_slice(&[2, 2, 0, 8]);
+        // red: run of 8 x 128
+        input.extend_from_slice(&[128 + 8, 128]);
+        // green: 8 literal values
+        input.extend_from_slice(&[8, 0, 0, 0, 0, 64, 64, 64, 64]);
+        // blue: run of 8 x 0
+        input.extend_from_slice(&[128 + 8, 0]);
+        // exponent: run of 8 x 129
+        input.extend_from_slice(&[128 + 8, 129]);
+
+        let res = read_hdr(input.as_slice()).unwrap();
+        assert_eq!(res.get_pixel(0, 0), Some(Color::new(1.0, 0.0, 0.0)));
+        assert_eq!(res.get_pixel(7, 0), Some(Color::new(1.0, 0.5, 0.0)));
+    }
+
+    #[test]
+    fn test_read_hdr_old_rle() {
+        let mut input = b"#?RADIANCE\n\n-Y 1 +X 4\n".to_vec();
+        input.extend_from_slice(&[128, 64, 0, 129, 1, 1, 1, 3]);
+        let res = read_hdr(input.as_slice()).unwrap();
+        for x in 0..4 {
+            assert_eq!(res.get_pixel(x, 0), Some(Color::new(1.0, 0.5, 0.0)));
+        }
+    }
+
+    #[test]
+    fn test_read_hdr_yields_error() {
+        let mut repeats = b"#?RADIANCE\n\n-Y 1 +X 2\n\x01\x02\x03\x80".to_vec();
+        for _ in 0..9 {
+            repeats.extend_from_slice(&[1, 1, 1, 0]);
+        }
+        let err = read_hdr(repeats.as_slice()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        let inputs: [&[u8]; 9] = [
+            b"PF\n1 1\n-1.0\n",
+            b"#?RADIANCE\nFORMAT=32-bit_rle_xyze\n\n-Y 1 +X 1\n\x00\x00\x00\x00",
+            b"#?RADIANCE\n\n+Y 1 +X 1\n\x00\x00\x00\x00",
+            b"#?RADIANCE\n\n-Y 1 +X 2\n\x00\x00\x00\x00",
+            b"#?RADIANCE\n\n-Y 1 +X 8\n\x02\x02\x00\x09",
+            b"#?RADIANCE\n\n-Y 100000 +X 100000\n",
+            b"#?RADIANCE\n\n-Y 18446744073709551615 +X 2\n",
+            b"#?RADIANCE\n\n-Y 0 +X 1000000000000\n",
+            b"#?RADIANCE\n\n-Y 18446744073709551615 +X 0\n",
+        ];
+        for input in inputs {
+            let err = read_hdr(input).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        }
+    }
+}
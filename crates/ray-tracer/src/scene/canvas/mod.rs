@@ -0,0 +1,534 @@
+//! # Canvas Module
+//!
+//! This module provides the `Canvas` struct, which represents a 2D grid of pixels.
+//! Each pixel is represented by a `Color`, and the canvas can be used to render
+//! images for the ray tracer challenge. The module also includes functionality
+//! to encode the canvas to any `io::Write` or file path, and to load images
+//! back into a canvas.
+//!
+//! ## Features
+//! - Create a canvas with default black pixels or a custom color.
+//! - Add or get pixels at specific coordinates.
+//! - Write the canvas as plain (`P3`) or binary (`P6`) PPM to any writer.
+//! - Encode and decode PNG images with 8 or 16 bits per channel.
+//! - Encode and decode unclamped radiance as PFM or Radiance HDR.
+//! - Tone map, gamma encode and dither 8-bit and 16-bit output through a
+//!   per-canvas `DisplayTransform`.
+//! - Save to or load from a path, selecting the format by file extension.
+//! - Encode a sequence of canvases as an animated GIF or APNG.
+
+mod animated;
+mod format;
+mod gif;
+mod hdr;
+mod pfm;
+mod png;
+mod ppm;
+mod tone_map;
+
+pub use animated::{AnimationFormat, AnimationOptions, save_animation, write_animation};
+pub use format::ImageFormat;
+pub use png::PngFormat;
+pub use ppm::PpmFormat;
+pub use tone_map::{DisplayTransform, ToneOperator, linear_to_srgb};
+
+use crate::shape::Color;
+use std::{
+    fs,
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::Path,
+};
+
+/// A 2D grid of pixels used for rendering images.
+///
+/// ## Fields
+/// - `width`: The width of the canvas in pixels.
+/// - `height`: The height of the canvas in pixels.
+/// - `pixels`: A vector storing the color of each pixel in row-major order.
+/// - `display`: The transform used to convert linear pixels for 8-bit and
+///   16-bit output.
+#[derive(Debug)]
+pub struct Canvas {
+    pub width: usize,
+    pub height: usize,
+    pixels: Vec<Color>,
+    display: DisplayTransform,
+}
+
+impl Canvas {
+    /// Creates a new canvas with the specified width and height.
+    /// All pixels are initialized to black.
+    pub fn new(width: usize, height: usize) -> Self {
+        let capacity: usize = width * height;
+        Self {
+            width,
+            height,
+            pixels: vec![Color::new_black(); capacity],
+            display: DisplayTransform::new(),
+        }
+    }
+
+    /// Creates a new canvas with the specified width, height and color.
+    pub fn new_with_color(width: usize, height: usize, color: Color) -> Self {
+        let capacity: usize = width * height;
+        Self {
+            width,
+            height,
+            pixels: vec![color; capacity],
+            display: DisplayTransform::new(),
+        }
+    }
+
+    /// Creates a canvas from pixels in row-major order.
+    pub(crate) fn from_pixels(width: usize, height: usize, pixels: Vec<Color>) -> Self {
+        debug_assert_eq!(pixels.len(), width * height);
+        Self {
+            width,
+            height,
+            pixels,
+            display: DisplayTransform::new(),
+        }
+    }
+}
+
+impl Canvas {
+    /// Sets the display transform used by the 8-bit and 16-bit exporters.
+    ///
+    /// Floating-point exporters (PFM and HDR) always write linear values.
+    pub fn set_display_transform(&mut self, display: DisplayTransform) {
+        self.display = display;
+    }
+
+    pub fn get_display_transform(&self) -> DisplayTransform {
+        self.display
+    }
+
+    /// Returns the pixel at the specified coordinates converted to 8-bit
+    /// display values.
+    pub fn get_display_pixel_u8(&self, x: usize, y: usize) -> Option<[u8; 3]> {
+        self.get_pixel(x, y)
+            .map(|color| self.display.to_u8(color, x, y))
+    }
+
+    /// Returns all pixels as opaque 8-bit RGBA display values in row-major
+    /// order, the layout GPU textures expect. The bytes are the ones an
+    /// 8-bit PNG of the canvas holds.
+    pub fn to_rgba8(&self) -> Vec<u8> {
+        let mut rgba = Vec::with_capacity(self.pixels.len() * 4);
+        for (i, color) in self.pixels.iter().enumerate() {
+            let (x, y) = (i % self.width.max(1), i / self.width.max(1));
+            rgba.extend_from_slice(&self.display.to_u8(*color, x, y));
+            rgba.push(255);
+        }
+        rgba
+    }
+}
+
+impl Canvas {
+    /// Sets the color of a pixel at the specified coordinates.
+    pub fn add_pixel(&mut self, x: usize, y: usize, color: Color) {
+        assert!(
+            x < self.width,
+            "x coordinate {} out of bounds (width: {})",
+            x,
+            self.width
+        );
+        assert!(
+            y < self.height,
+            "y coordinate {} out of bounds (height: {})",
+            y,
+            self.height
+        );
+        let index = self.width * y + x;
+        self.pixels[index] = color;
+    }
+
+    /// Returns the color of a pixel at the specified coordinates.
+    pub fn get_pixel(&self, x: usize, y: usize) -> Option<Color> {
+        match self.get_index(x, y) {
+            Ok(i) => Some(self.pixels[i]),
+            Err(_) => None,
+        }
+    }
+
+    /// Calculates the index of a pixel using the specified coordinates.
+    pub fn get_index(&self, x: usize, y: usize) -> Result<usize, &'static str> {
+        if self.pixels.capacity() == 0 {
+            return Err("Error: pixels vector capacity equal zero.");
+        }
+        if x >= self.width || y >= self.height {
+            return Err("Error: coordinate is out of bounds.");
+        }
+        Ok(self.width * y + x)
+    }
+}
+
+impl Canvas {
+    /// Writes the canvas as a plain (`P3`) PPM (Portable Pixmap) image.
+    ///
+    /// ## Returns
+    /// A `Result` indicating success or failure of the underlying writer.
+    pub fn write_ppm<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+        ppm::write_ppm(self, PpmFormat::Ascii, writer)
+    }
+
+    /// Writes the canvas as a binary (`P6`) PPM image.
+    ///
+    /// Binary output is about a quarter of the size of plain output and much
+    /// faster to write, which matters for large images.
+    pub fn write_ppm_binary<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+        ppm::write_ppm(self, PpmFormat::Binary, writer)
+    }
+
+    /// Writes the canvas as a PNG image with the given pixel layout.
+    pub fn write_png<W: Write>(&self, writer: &mut W, format: PngFormat) -> Result<(), io::Error> {
+        png::write_png(self, format, writer)
+    }
+
+    /// Writes the canvas as a Portable Float Map without clamping.
+    pub fn write_pfm<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+        pfm::write_pfm(self, writer)
+    }
+
+    /// Writes the canvas as a Radiance RGBE (`.hdr`) image without clamping
+    /// values above `1.0`. Negative values are written as zero.
+    pub fn write_hdr<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+        hdr::write_hdr(self, writer)
+    }
+
+    /// Writes the canvas to `writer` encoded in the given format.
+    pub fn write_to<W: Write>(&self, writer: &mut W, format: ImageFormat) -> Result<(), io::Error> {
+        match format {
+            ImageFormat::Ppm(variant) => ppm::write_ppm(self, variant, writer),
+            ImageFormat::Png(variant) => png::write_png(self, variant, writer),
+            ImageFormat::Pfm => pfm::write_pfm(self, writer),
+            ImageFormat::Hdr => hdr::write_hdr(self, writer),
+        }
+    }
+
+    /// Saves the canvas to exactly `path`, choosing the format from its
+    /// extension.
+    ///
+    /// Parent directories are not created.
+    ///
+    /// ## Returns
+    /// An error of kind `Unsupported` if the extension is unknown, or the
+    /// error raised while creating or writing the file.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), io::Error> {
+        let format = ImageFormat::from_path(&path).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::Unsupported,
+                "Error: unsupported image file extension.",
+            )
+        })?;
+        self.save_as(path, format)
+    }
+
+    /// Saves the canvas to exactly `path` in the given format, regardless of
+    /// the file extension.
+    pub fn save_as<P: AsRef<Path>>(&self, path: P, format: ImageFormat) -> Result<(), io::Error> {
+        let mut f = BufWriter::new(fs::File::create(path)?);
+        self.write_to(&mut f, format)?;
+        f.flush()
+    }
+}
+
+impl Canvas {
+    /// Reads a plain (`P3`) or binary (`P6`) PPM image into a new canvas.
+    ///
+    /// Comments in the header are skipped and channel values are scaled by
+    /// the file's maximum color value, so the result is in `0.0..=1.0`.
+    ///
+    /// ## Returns
+    /// The decoded canvas, or an error of kind `InvalidData` if the input is
+    /// not a well-formed PPM image.
+    pub fn from_ppm<R: Read>(reader: R) -> Result<Self, io::Error> {
+        ppm::read_ppm(reader)
+    }
+
+    /// Reads a PPM image from the file at `path`.
+    pub fn load_ppm<P: AsRef<Path>>(path: P) -> Result<Self, io::Error> {
+        let f = fs::File::open(path)?;
+        Canvas::from_ppm(BufReader::new(f))
+    }
+
+    /// Reads a PNG image into a new canvas.
+    ///
+    /// Any PNG color type and bit depth is accepted; grayscale is replicated
+    /// into all channels and the alpha channel is ignored.
+    pub fn from_png<R: Read>(reader: R) -> Result<Self, io::Error> {
+        png::read_png(reader)
+    }
+
+    /// Reads a color or grayscale Portable Float Map into a new canvas.
+    pub fn from_pfm<R: Read>(reader: R) -> Result<Self, io::Error> {
+        pfm::read_pfm(reader)
+    }
+
+    /// Reads a Radiance RGBE (`.hdr`) image into a new canvas.
+    pub fn from_hdr<R: Read>(reader: R) -> Result<Self, io::Error> {
+        hdr::read_hdr(reader)
+    }
+
+    /// Reads an image from `path` in the given format.
+    pub fn load_as<P: AsRef<Path>>(path: P, format: ImageFormat) -> Result<Self, io::Error> {
+        let f = BufReader::new(fs::File::open(path)?);
+        match format {
+            ImageFormat::Ppm(_) => ppm::read_ppm(f),
+            ImageFormat::Png(_) => png::read_png(f),
+            ImageFormat::Pfm => pfm::read_pfm(f),
+            ImageFormat::Hdr => hdr::read_hdr(f),
+        }
+    }
+
+    /// Reads an image from `path`, choosing the decoder from its extension.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, io::Error> {
+        let format = ImageFormat::from_path(&path).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::Unsupported,
+                "Error: unsupported image file extension.",
+            )
+        })?;
+        Canvas::load_as(path, format)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ppm::RENDER_COLUMN_MAX;
+    use super::*;
+
+    #[test]
+    fn test_new() {
+        let _w: usize = 10;
+        let _h: usize = 20;
+        let _c: Color = Color::new_black();
+        let res = Canvas::new(_w, _h);
+        assert_eq!(res.width, _w);
+        assert_eq!(res.height, _h);
+        assert_eq!(res.pixels.capacity(), _w * _h);
+        assert_eq!(res.pixels[0], _c);
+    }
+
+    #[test]
+    fn test_get_index() {
+        let _w: usize = 10;
+        let _h: usize = 20;
+        let res = Canvas::new(_w, _h);
+
+        // Valid coordinates
+        assert_eq!(res.get_index(0, 0), Ok(0));
+        assert_eq!(res.get_index(1, 0), Ok(1));
+        assert_eq!(res.get_index(0, 1), Ok(10));
+        assert_eq!(res.get_index(9, 19), Ok(199));
+
+        // Error x or y out-of-bounds
+        assert_eq!(
+            res.get_index(10, 0),
+            Err("Error: coordinate is out of bounds.")
+        );
+    }
+
+    #[test]
+    fn test_canvas_custom_color() {
+        let _w: usize = 3;
+        let _h: usize = 3;
+        let _c: Color = Color::new(0.2, 0.4, 0.6);
+        let canvas = Canvas::new_with_color(_w, _h, _c);
+
+        for y in 0.._h {
+            for x in 0.._w {
+                assert_eq!(canvas.get_pixel(x, y), Some(_c));
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_add_pixel_out_of_bounds() {
+        let _w: usize = 5;
+        let _h: usize = 5;
+        let _c: Color = Color::new_white();
+        let mut canvas = Canvas::new(_w, _h);
+        canvas.add_pixel(6, 0, _c);
+    }
+
+    #[test]
+    fn test_get_pixel_out_of_bounds() {
+        let _w: usize = 5;
+        let _h: usize = 5;
+        let canvas = Canvas::new(_w, _h);
+
+        assert_eq!(canvas.get_pixel(6, 0), None);
+        assert_eq!(canvas.get_pixel(0, 6), None);
+        assert_eq!(canvas.get_pixel(6, 6), None);
+    }
+
+    #[test]
+    fn test_write_ppm() {
+        let _w: usize = 5;
+        let _h: usize = 3;
+        let _c: Color = Color::new(1.0, 0.5, 0.25);
+        let mut canvas = Canvas::new_with_color(_w, _h, _c);
+
+        let mut out: Vec<u8> = Vec::new();
+        canvas.write_ppm(&mut out).unwrap();
+        let ppm_content = String::from_utf8(out).unwrap();
+        assert!(ppm_content.starts_with("P3\n5 3\n255\n255 188 137"));
+
+        canvas.set_display_transform(DisplayTransform::linear());
+        let mut out: Vec<u8> = Vec::new();
+        canvas.write_ppm(&mut out).unwrap();
+        let ppm_content = String::from_utf8(out).unwrap();
+        assert!(ppm_content.starts_with("P3\n5 3\n255\n255 128 64"));
+        assert!(ppm_content.ends_with('\n'));
+
+        let mut out: Vec<u8> = Vec::new();
+        canvas.write_ppm_binary(&mut out).unwrap();
+        assert!(out.starts_with(b"P6\n5 3\n255\n"));
+        assert_eq!(out.len(), "P6\n5 3\n255\n".len() + _w * _h * 3);
+    }
+
+    #[test]
+    fn test_write_ppm_empty_canvas() {
+        let canvas = Canvas::new(0, 0);
+
+        let mut out: Vec<u8> = Vec::new();
+        let result = canvas.write_ppm(&mut out);
+        assert!(result.is_ok());
+        assert_eq!(out, b"P3\n0 0\n255\n");
+    }
+
+    #[test]
+    fn test_write_ppm_line_wrapping() {
+        let _w: usize = 10;
+        let _h: usize = 2;
+        let _c: Color = Color::new(1.0, 0.8, 0.6);
+        let canvas = Canvas::new_with_color(_w, _h, _c);
+
+        let mut out: Vec<u8> = Vec::new();
+        canvas.write_ppm(&mut out).unwrap();
+
+        let ppm_content = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = ppm_content.lines().collect();
+        assert!(lines.iter().all(|line| line.len() <= RENDER_COLUMN_MAX));
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let _c: Color = Color::new(1.0, 0.0, 0.2);
+        let mut canvas = Canvas::new_with_color(4, 2, _c);
+        canvas.set_display_transform(DisplayTransform::linear());
+
+        let path = dir.path().join("round_trip.ppm");
+        let result = canvas.save(&path);
+        assert!(result.is_ok());
+        assert!(path.exists());
+
+        let loaded = Canvas::load(&path).unwrap();
+        assert_eq!(loaded.width, 4);
+        assert_eq!(loaded.height, 2);
+        assert_eq!(loaded.get_pixel(3, 1), Some(_c));
+
+        let path = dir.path().join("plain.out");
+        let result = canvas.save_as(&path, ImageFormat::Ppm(PpmFormat::Ascii));
+        assert!(result.is_ok());
+        assert!(fs::read_to_string(&path).unwrap().starts_with("P3\n"));
+        let loaded = Canvas::load_ppm(&path).unwrap();
+        assert_eq!(loaded.get_pixel(0, 0), Some(_c));
+    }
+
+    #[test]
+    fn test_save_and_load_png() {
+        let dir = tempfile::tempdir().unwrap();
+        let _c: Color = Color::new(0.2, 0.4, 0.6);
+        let mut canvas = Canvas::new_with_color(3, 5, _c);
+        canvas.set_display_transform(DisplayTransform::linear());
+
+        let path = dir.path().join("image.png");
+        canvas.save(&path).unwrap();
+        let loaded = Canvas::load(&path).unwrap();
+        assert_eq!(loaded.width, 3);
+        assert_eq!(loaded.height, 5);
+        assert_eq!(loaded.get_pixel(2, 4), Some(_c));
+
+        let path = dir.path().join("image_16.png");
+        canvas
+            .save_as(&path, ImageFormat::Png(PngFormat::Rgba16))
+            .unwrap();
+        let loaded = Canvas::load(&path).unwrap();
+        assert_eq!(loaded.get_pixel(0, 0), Some(_c));
+    }
+
+    #[test]
+    fn test_save_and_load_high_dynamic_range() {
+        let dir = tempfile::tempdir().unwrap();
+        let _c: Color = Color::new(4.0, 1.5, 0.5);
+        let canvas = Canvas::new_with_color(2, 2, _c);
+
+        for name in ["image.pfm", "image.hdr"] {
+            let path = dir.path().join(name);
+            canvas.save(&path).unwrap();
+            let loaded = Canvas::load(&path).unwrap();
+            assert_eq!(loaded.width, 2);
+            assert_eq!(loaded.height, 2);
+            assert_eq!(loaded.get_pixel(1, 1), Some(_c));
+        }
+    }
+
+    #[test]
+    fn test_display_transform() {
+        let mut canvas = Canvas::new_with_color(2, 1, Color::new(0.5, 2.0, 0.0));
+        assert_eq!(canvas.get_display_transform(), DisplayTransform::new());
+        assert_eq!(canvas.get_display_pixel_u8(0, 0), Some([188, 255, 0]));
+
+        canvas.set_display_transform(DisplayTransform::srgb(ToneOperator::Reinhard));
+        assert_eq!(canvas.get_display_pixel_u8(1, 0), Some([156, 213, 0]));
+        assert_eq!(canvas.get_display_pixel_u8(2, 0), None);
+
+        let mut out: Vec<u8> = Vec::new();
+        canvas.write_ppm_binary(&mut out).unwrap();
+        assert!(out.ends_with(&[156, 213, 0, 156, 213, 0]));
+    }
+
+    #[test]
+    fn test_to_rgba8() {
+        let mut canvas = Canvas::new(2, 2);
+        canvas.add_pixel(1, 0, Color::new(0.5, 2.0, 0.0));
+        canvas.add_pixel(0, 1, Color::new(0.0, 0.0, 1.0));
+        assert_eq!(
+            canvas.to_rgba8(),
+            [0, 0, 0, 255, 188, 255, 0, 255, 0, 0, 255, 255, 0, 0, 0, 255]
+        );
+        assert!(Canvas::new(0, 0).to_rgba8().is_empty());
+    }
+
+    #[test]
+    fn test_save_yields_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let canvas = Canvas::new(2, 2);
+
+        let err = canvas.save(dir.path().join("image.unknown")).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::Unsupported);
+
+        // the exact path is used, missing directories are not created
+        let path = dir.path().join("missing").join("image.ppm");
+        let err = canvas.save(&path).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+        assert!(!dir.path().join("missing").exists());
+    }
+
+    #[test]
+    fn test_canvas_default_color() {
+        let _w: usize = 4;
+        let _h: usize = 4;
+        let canvas = Canvas::new(_w, _h);
+
+        for y in 0.._h {
+            for x in 0.._w {
+                assert_eq!(canvas.get_pixel(x, y), Some(Color::new_black()));
+            }
+        }
+    }
+}
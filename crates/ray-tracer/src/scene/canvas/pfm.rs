@@ -0,0 +1,153 @@
+//! # PFM Module
+//!
+//! Encoding and decoding of Portable Float Map images. Channels are stored
+//! as unclamped 32-bit floats, so radiance values above `1.0` (and below
+//! `0.0`) survive a round trip.
+//!
+//! ## Features
+//! - Write a canvas as a little-endian color (`PF`) float map.
+//! - Read color (`PF`) and grayscale (`Pf`) float maps of either byte order.
+
+use crate::scene::Canvas;
+use crate::scene::canvas::ppm::{HeaderParser, invalid_data};
+use crate::shape::Color;
+use std::io::{self, Read, Write};
+
+/// Writes the canvas as a little-endian color PFM image.
+///
+/// PFM stores scanlines from the bottom of the image to the top.
+pub(crate) fn write_pfm<W: Write>(canvas: &Canvas, writer: &mut W) -> io::Result<()> {
+    writeln!(writer, "PF")?;
+    writeln!(writer, "{} {}", canvas.width, canvas.height)?;
+    // a negative scale marks little-endian data
+    writeln!(writer, "-1.0")?;
+
+    let mut row_bytes: Vec<u8> = Vec::with_capacity(canvas.width * 12);
+    for row in canvas.pixels.chunks(canvas.width.max(1)).rev() {
+        row_bytes.clear();
+        for px in row {
+            for channel in [px.get_red(), px.get_green(), px.get_blue()] {
+                row_bytes.extend_from_slice(&(channel as f32).to_le_bytes());
+            }
+        }
+        writer.write_all(&row_bytes)?;
+    }
+    Ok(())
+}
+
+/// Reads a `PF` or `Pf` image into a new canvas.
+///
+/// The magnitude of the scale factor is ignored; its sign selects the byte
+/// order. Grayscale maps are replicated into all three channels.
+pub(crate) fn read_pfm<R: Read>(mut reader: R) -> io::Result<Canvas> {
+    let mut data: Vec<u8> = Vec::new();
+    reader.read_to_end(&mut data)?;
+
+    let mut parser = HeaderParser::new(&data);
+    let channels = match parser.next_token()? {
+        b"PF" => 3,
+        b"Pf" => 1,
+        _ => return Err(invalid_data("PFM: unsupported magic number.")),
+    };
+    let width: usize = parser.next_number()?;
+    let height: usize = parser.next_number()?;
+    let scale: f64 = parser.next_number()?;
+    if scale == 0.0 || !scale.is_finite() {
+        return Err(invalid_data("PFM: invalid scale factor."));
+    }
+    let little_endian = scale < 0.0;
+
+    let row_len = width
+        .checked_mul(channels * 4)
+        .ok_or_else(|| invalid_data("PFM: image dimensions are too large."))?;
+    let raster = parser.raster()?;
+    if raster.len() < row_len.saturating_mul(height) {
+        return Err(invalid_data("PFM: unexpected end of raster data."));
+    }
+
+    let mut pixels: Vec<Color> = Vec::with_capacity(width * height);
+    if row_len > 0 {
+        // scanlines are stored bottom to top
+        for row in raster.chunks_exact(row_len).take(height).rev() {
+            let values: Vec<f64> = row
+                .chunks_exact(4)
+                .map(|b| {
+                    let bytes = [b[0], b[1], b[2], b[3]];
+                    if little_endian {
+                        f32::from_le_bytes(bytes) as f64
+                    } else {
+                        f32::from_be_bytes(bytes) as f64
+                    }
+                })
+                .collect();
+            for px in values.chunks_exact(channels) {
+                pixels.push(match px {
+                    [v] => Color::new(*v, *v, *v),
+                    _ => Color::new(px[0], px[1], px[2]),
+                });
+            }
+        }
+    }
+
+    Ok(Canvas::from_pixels(width, height, pixels))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_pfm() {
+        let mut canvas = Canvas::new(1, 2);
+        canvas.add_pixel(0, 0, Color::new(1.0, 2.0, 3.0));
+        let mut out: Vec<u8> = Vec::new();
+        write_pfm(&canvas, &mut out).unwrap();
+
+        let mut expected = b"PF\n1 2\n-1.0\n".to_vec();
+        // bottom row first
+        for v in [0.0_f32, 0.0, 0.0, 1.0, 2.0, 3.0] {
+            expected.extend_from_slice(&v.to_le_bytes());
+        }
+        assert_eq!(out, expected);
+    }
+
+    #[test]
+    fn test_pfm_round_trip() {
+        let mut canvas = Canvas::new(3, 2);
+        canvas.add_pixel(0, 0, Color::new(12.5, 0.25, -0.5));
+        canvas.add_pixel(2, 1, Color::new(0.2, 0.4, 1000.0));
+        let mut out: Vec<u8> = Vec::new();
+        write_pfm(&canvas, &mut out).unwrap();
+
+        let res = read_pfm(out.as_slice()).unwrap();
+        assert_eq!(res.width, 3);
+        assert_eq!(res.height, 2);
+        assert_eq!(res.get_pixel(0, 0), Some(Color::new(12.5, 0.25, -0.5)));
+        assert_eq!(res.get_pixel(1, 0), Some(Color::new_black()));
+        assert_eq!(res.get_pixel(2, 1), Some(Color::new(0.2, 0.4, 1000.0)));
+    }
+
+    #[test]
+    fn test_read_pfm_grayscale_big_endian() {
+        let mut input = b"Pf\n2 1\n1.0\n".to_vec();
+        input.extend_from_slice(&0.5_f32.to_be_bytes());
+        input.extend_from_slice(&4.0_f32.to_be_bytes());
+        let res = read_pfm(input.as_slice()).unwrap();
+        assert_eq!(res.get_pixel(0, 0), Some(Color::new(0.5, 0.5, 0.5)));
+        assert_eq!(res.get_pixel(1, 0), Some(Color::new(4.0, 4.0, 4.0)));
+    }
+
+    #[test]
+    fn test_read_pfm_yields_error() {
+        let inputs: [&[u8]; 4] = [
+            b"P6\n1 1\n-1.0\n\x00\x00\x00\x00",
+            b"PF\n1 1\n0.0\n\x00\x00\x00\x00",
+            b"Pf\n1 1\n-1.0\n\x00\x00",
+            b"Pf\n1 1\n-1.0",
+        ];
+        for input in inputs {
+            let err = read_pfm(input).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        }
+    }
+}
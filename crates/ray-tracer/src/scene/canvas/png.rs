@@ -0,0 +1,278 @@
+//! # PNG Module
+//!
+//! Encoding and decoding of Portable Network Graphics images using the
+//! `png` crate.
+//!
+//! ## Features
+//! - Encode a canvas as RGB or RGBA with 8 or 16 bits per channel.
+//! - Encode a sequence of canvases as an animated PNG (APNG).
+//! - Decode grayscale, RGB, indexed and alpha images of any bit depth.
+
+use crate::scene::Canvas;
+use crate::shape::Color;
+use std::io::{self, Cursor, Read, Write};
+
+/// The PNG pixel layout used when encoding a canvas.
+///
+/// The canvas has no transparency, so the alpha channel of the RGBA
+/// variants is always fully opaque.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PngFormat {
+    /// Red, green and blue with 8 bits per channel.
+    Rgb8,
+    /// Red, green, blue and alpha with 8 bits per channel.
+    Rgba8,
+    /// Red, green and blue with 16 bits per channel.
+    Rgb16,
+    /// Red, green, blue and alpha with 16 bits per channel.
+    Rgba16,
+}
+
+impl PngFormat {
+    fn color_type(&self) -> ::png::ColorType {
+        match self {
+            PngFormat::Rgb8 | PngFormat::Rgb16 => ::png::ColorType::Rgb,
+            PngFormat::Rgba8 | PngFormat::Rgba16 => ::png::ColorType::Rgba,
+        }
+    }
+
+    fn bit_depth(&self) -> ::png::BitDepth {
+        match self {
+            PngFormat::Rgb8 | PngFormat::Rgba8 => ::png::BitDepth::Eight,
+            PngFormat::Rgb16 | PngFormat::Rgba16 => ::png::BitDepth::Sixteen,
+        }
+    }
+}
+
+/// Writes the canvas as a PNG image with the given pixel layout.
+pub(crate) fn write_png<W: Write>(
+    canvas: &Canvas,
+    format: PngFormat,
+    writer: &mut W,
+) -> io::Result<()> {
+    let width = u32::try_from(canvas.width).map_err(|_| too_large())?;
+    let height = u32::try_from(canvas.height).map_err(|_| too_large())?;
+
+    let mut encoder = ::png::Encoder::new(writer, width, height);
+    encoder.set_color(format.color_type());
+    encoder.set_depth(format.bit_depth());
+    let mut png_writer = encoder.write_header()?;
+    png_writer.write_image_data(&encode_samples(canvas, format))?;
+    png_writer.finish()?;
+    Ok(())
+}
+
+/// Writes equally sized frames as a looping 8-bit RGB animated PNG, each
+/// shown for `delay` seconds expressed as a fraction.
+pub(crate) fn write_apng<W: Write>(
+    frames: &[Canvas],
+    delay: (u16, u16),
+    writer: &mut W,
+) -> io::Result<()> {
+    let first = frames.first().ok_or_else(no_frames)?;
+    let width = u32::try_from(first.width).map_err(|_| too_large())?;
+    let height = u32::try_from(first.height).map_err(|_| too_large())?;
+    let count = u32::try_from(frames.len()).map_err(|_| too_large())?;
+
+    let mut encoder = ::png::Encoder::new(writer, width, height);
+    encoder.set_color(::png::ColorType::Rgb);
+    encoder.set_depth(::png::BitDepth::Eight);
+    // zero plays loops forever
+    encoder.set_animated(count, 0)?;
+    encoder.set_frame_delay(delay.0, delay.1)?;
+    let mut png_writer = encoder.write_header()?;
+    for frame in frames {
+        png_writer.write_image_data(&encode_samples(frame, PngFormat::Rgb8))?;
+    }
+    png_writer.finish()?;
+    Ok(())
+}
+
+/// Packs the canvas pixels into big-endian PNG samples, converting them
+/// with the canvas display transform.
+fn encode_samples(canvas: &Canvas, format: PngFormat) -> Vec<u8> {
+    let alpha = format.color_type() == ::png::ColorType::Rgba;
+    let mut data: Vec<u8> = Vec::with_capacity(canvas.pixels.len() * 8);
+
+    for (y, row) in canvas.pixels.chunks(canvas.width.max(1)).enumerate() {
+        for (x, px) in row.iter().enumerate() {
+            match format.bit_depth() {
+                ::png::BitDepth::Sixteen => {
+                    for channel in canvas.display.to_u16(*px) {
+                        data.extend_from_slice(&channel.to_be_bytes());
+                    }
+                    if alpha {
+                        data.extend_from_slice(&u16::MAX.to_be_bytes());
+                    }
+                }
+                _ => {
+                    data.extend_from_slice(&canvas.display.to_u8(*px, x, y));
+                    if alpha {
+                        data.push(u8::MAX);
+                    }
+                }
+            }
+        }
+    }
+
+    data
+}
+
+/// Reads a PNG image into a new canvas.
+///
+/// Palette and low bit depth images are expanded, grayscale is replicated
+/// into all three channels and the alpha channel is ignored. Channel values
+/// are scaled into `0.0..=1.0`.
+pub(crate) fn read_png<R: Read>(mut reader: R) -> io::Result<Canvas> {
+    let mut data: Vec<u8> = Vec::new();
+    reader.read_to_end(&mut data)?;
+
+    let mut decoder = ::png::Decoder::new(Cursor::new(data));
+    decoder.set_transformations(::png::Transformations::EXPAND);
+    let mut png_reader = decoder.read_info()?;
+    let size = png_reader.output_buffer_size().ok_or_else(too_large)?;
+    let mut buf = vec![0u8; size];
+    let info = png_reader.next_frame(&mut buf)?;
+    let buf = &buf[..info.buffer_size()];
+
+    let channels = info.color_type.samples();
+    let (bytes_per_sample, scale) = match info.bit_depth {
+        ::png::BitDepth::Sixteen => (2, 65535.0),
+        _ => (1, 255.0),
+    };
+
+    let sample = |px: &[u8], i: usize| -> f64 {
+        let value = match bytes_per_sample {
+            2 => u16::from_be_bytes([px[i * 2], px[i * 2 + 1]]) as f64,
+            _ => px[i] as f64,
+        };
+        value / scale
+    };
+
+    let width = info.width as usize;
+    let height = info.height as usize;
+    let mut pixels: Vec<Color> = Vec::with_capacity(width * height);
+    for row in buf.chunks_exact(info.line_size).take(height) {
+        for px in row.chunks_exact(channels * bytes_per_sample).take(width) {
+            let color = match info.color_type {
+                ::png::ColorType::Grayscale | ::png::ColorType::GrayscaleAlpha => {
+                    let v = sample(px, 0);
+                    Color::new(v, v, v)
+                }
+                _ => Color::new(sample(px, 0), sample(px, 1), sample(px, 2)),
+            };
+            pixels.push(color);
+        }
+    }
+
+    Ok(Canvas::from_pixels(width, height, pixels))
+}
+
+fn no_frames() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        "PNG: an animation needs at least one frame.",
+    )
+}
+
+fn too_large() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        "PNG: image dimensions are too large.",
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scene::canvas::DisplayTransform;
+
+    fn encode(canvas: &Canvas, format: PngFormat) -> Vec<u8> {
+        let mut out: Vec<u8> = Vec::new();
+        write_png(canvas, format, &mut out).unwrap();
+        out
+    }
+
+    fn test_canvas() -> Canvas {
+        let mut canvas = Canvas::new(3, 2);
+        canvas.set_display_transform(DisplayTransform::linear());
+        canvas.add_pixel(0, 0, Color::new(1.0, 0.0, 0.0));
+        canvas.add_pixel(1, 0, Color::new(0.2, 0.4, 0.6));
+        canvas.add_pixel(2, 1, Color::new(1.5, -0.5, 1.0));
+        canvas
+    }
+
+    #[test]
+    fn test_write_png_signature() {
+        let out = encode(&test_canvas(), PngFormat::Rgb8);
+        assert!(out.starts_with(&[0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a]));
+    }
+
+    #[test]
+    fn test_png_round_trip() {
+        for format in [
+            PngFormat::Rgb8,
+            PngFormat::Rgba8,
+            PngFormat::Rgb16,
+            PngFormat::Rgba16,
+        ] {
+            let res = read_png(encode(&test_canvas(), format).as_slice()).unwrap();
+            assert_eq!(res.width, 3);
+            assert_eq!(res.height, 2);
+            assert_eq!(res.get_pixel(0, 0), Some(Color::new(1.0, 0.0, 0.0)));
+            assert_eq!(res.get_pixel(0, 1), Some(Color::new_black()));
+            assert_eq!(res.get_pixel(2, 1), Some(Color::new(1.0, 0.0, 1.0)));
+        }
+
+        let res = read_png(encode(&test_canvas(), PngFormat::Rgb8).as_slice()).unwrap();
+        assert_eq!(
+            res.get_pixel(1, 0),
+            Some(Color::new(51.0 / 255.0, 102.0 / 255.0, 153.0 / 255.0))
+        );
+        let res = read_png(encode(&test_canvas(), PngFormat::Rgb16).as_slice()).unwrap();
+        assert_eq!(res.get_pixel(1, 0), Some(Color::new(0.2, 0.4, 0.6)));
+    }
+
+    #[test]
+    fn test_read_png_grayscale() {
+        let mut out: Vec<u8> = Vec::new();
+        let mut encoder = ::png::Encoder::new(&mut out, 2, 1);
+        encoder.set_color(::png::ColorType::Grayscale);
+        encoder.set_depth(::png::BitDepth::Eight);
+        let mut writer = encoder.write_header().unwrap();
+        writer.write_image_data(&[0, 255]).unwrap();
+        writer.finish().unwrap();
+
+        let res = read_png(out.as_slice()).unwrap();
+        assert_eq!(res.get_pixel(0, 0), Some(Color::new_black()));
+        assert_eq!(res.get_pixel(1, 0), Some(Color::new_white()));
+    }
+
+    #[test]
+    fn test_write_apng() {
+        let mut second = test_canvas();
+        second.add_pixel(0, 0, Color::new(0.0, 0.0, 1.0));
+        let mut out: Vec<u8> = Vec::new();
+        write_apng(&[test_canvas(), second], (100, 2400), &mut out).unwrap();
+
+        let mut reader = ::png::Decoder::new(Cursor::new(out)).read_info().unwrap();
+        let control = reader.info().animation_control.unwrap();
+        assert_eq!((control.num_frames, control.num_plays), (2, 0));
+        let mut buf = vec![0u8; reader.output_buffer_size().unwrap()];
+        reader.next_frame(&mut buf).unwrap();
+        assert_eq!(buf[..3], [255, 0, 0]);
+        reader.next_frame(&mut buf).unwrap();
+        let frame = reader.info().frame_control.unwrap();
+        assert_eq!((frame.delay_num, frame.delay_den), (100, 2400));
+        assert_eq!(buf[..3], [0, 0, 255]);
+
+        let err = write_apng(&[], (1, 24), &mut Vec::new()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn test_read_png_yields_error() {
+        let err = read_png(&b"P6\n1 1\n255\n\x00\x00\x00"[..]);
+        assert!(err.is_err());
+    }
+}
@@ -0,0 +1,358 @@
+//! # PPM Module
+//!
+//! Encoding and decoding of Portable Pixmap images. Both the plain (`P3`,
+//! ASCII) and the raw (`P6`, binary) variants are supported.
+//!
+//! ## Features
+//! - Stream a canvas as `P3` or `P6` without building intermediate strings.
+//! - Parse `P3` and `P6` files, including `#` comments in the header and
+//!   any maximum color value in `1..=65535`.
+
+use crate::scene::Canvas;
+use crate::shape::Color;
+use std::io::{self, Read, Write};
+
+/// Maximum line length of a plain PPM file.
+pub(crate) const RENDER_COLUMN_MAX: usize = 70;
+/// Maximum color value written by the encoder.
+const PPM_MAX_VALUE: usize = 255;
+
+/// The PPM variant used when encoding a canvas.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PpmFormat {
+    /// Plain PPM (`P3`): whitespace separated decimal values.
+    Ascii,
+    /// Raw PPM (`P6`): one byte per channel.
+    Binary,
+}
+
+impl PpmFormat {
+    /// Returns the magic number that starts a file of this format.
+    pub fn magic(&self) -> &'static str {
+        match self {
+            PpmFormat::Ascii => "P3",
+            PpmFormat::Binary => "P6",
+        }
+    }
+}
+
+/// Writes the canvas as a PPM image in the given format, converting pixels
+/// with the canvas display transform.
+pub(crate) fn write_ppm<W: Write>(
+    canvas: &Canvas,
+    format: PpmFormat,
+    writer: &mut W,
+) -> io::Result<()> {
+    writeln!(writer, "{}", format.magic())?;
+    writeln!(writer, "{} {}", canvas.width, canvas.height)?;
+    writeln!(writer, "{}", PPM_MAX_VALUE)?;
+
+    match format {
+        PpmFormat::Ascii => write_ascii_raster(canvas, writer),
+        PpmFormat::Binary => write_binary_raster(canvas, writer),
+    }
+}
+
+/// Writes the pixels as decimal values, one image row per line group,
+/// wrapping lines so that none exceeds `RENDER_COLUMN_MAX` characters.
+fn write_ascii_raster<W: Write>(canvas: &Canvas, writer: &mut W) -> io::Result<()> {
+    use std::fmt::Write as _;
+
+    let mut line = String::with_capacity(RENDER_COLUMN_MAX + 1);
+    let mut value = String::with_capacity(3);
+
+    for (y, row) in canvas.pixels.chunks(canvas.width.max(1)).enumerate() {
+        for (x, px) in row.iter().enumerate() {
+            for channel in canvas.display.to_u8(*px, x, y) {
+                value.clear();
+                let _ = write!(value, "{}", channel);
+                if !line.is_empty() && line.len() + 1 + value.len() > RENDER_COLUMN_MAX {
+                    line.push('\n');
+                    writer.write_all(line.as_bytes())?;
+                    line.clear();
+                }
+                if !line.is_empty() {
+                    line.push(' ');
+                }
+                line.push_str(&value);
+            }
+        }
+        line.push('\n');
+        writer.write_all(line.as_bytes())?;
+        line.clear();
+    }
+
+    Ok(())
+}
+
+/// Writes the pixels as raw bytes, one byte per channel.
+fn write_binary_raster<W: Write>(canvas: &Canvas, writer: &mut W) -> io::Result<()> {
+    let mut row_bytes: Vec<u8> = Vec::with_capacity(canvas.width * 3);
+    for (y, row) in canvas.pixels.chunks(canvas.width.max(1)).enumerate() {
+        row_bytes.clear();
+        for (x, px) in row.iter().enumerate() {
+            row_bytes.extend_from_slice(&canvas.display.to_u8(*px, x, y));
+        }
+        writer.write_all(&row_bytes)?;
+    }
+    Ok(())
+}
+
+/// Reads a `P3` or `P6` image into a new canvas.
+///
+/// Channel values are scaled from `0..=maxval` into `0.0..=1.0`.
+pub(crate) fn read_ppm<R: Read>(mut reader: R) -> io::Result<Canvas> {
+    let mut data: Vec<u8> = Vec::new();
+    reader.read_to_end(&mut data)?;
+
+    let mut parser = HeaderParser::new(&data);
+    let format = match parser.next_token()? {
+        b"P3" => PpmFormat::Ascii,
+        b"P6" => PpmFormat::Binary,
+        _ => return Err(invalid_data("PPM: unsupported magic number.")),
+    };
+    let width: usize = parser.next_number()?;
+    let height: usize = parser.next_number()?;
+    let max_value: usize = parser.next_number()?;
+    if max_value == 0 || max_value > u16::MAX as usize {
+        return Err(invalid_data("PPM: maximum color value out of range."));
+    }
+
+    let too_large = || invalid_data("PPM: image dimensions are too large.");
+    let sample_count = width
+        .checked_mul(height)
+        .and_then(|count| count.checked_mul(3))
+        .ok_or_else(too_large)?;
+
+    let samples: Vec<usize> = match format {
+        PpmFormat::Ascii => {
+            // grown as values are read, so a short file fails before a
+            // large header can allocate
+            let mut samples = Vec::new();
+            for _ in 0..sample_count {
+                samples.push(parser.next_number()?);
+            }
+            samples
+        }
+        PpmFormat::Binary => {
+            let raster = parser.raster()?;
+            let bytes_per_sample = if max_value > 255 { 2 } else { 1 };
+            let raster_len = sample_count
+                .checked_mul(bytes_per_sample)
+                .ok_or_else(too_large)?;
+            if raster.len() < raster_len {
+                return Err(invalid_data("PPM: unexpected end of raster data."));
+            }
+            raster[..raster_len]
+                .chunks_exact(bytes_per_sample)
+                .map(|sample| match sample {
+                    [hi, lo] => u16::from_be_bytes([*hi, *lo]) as usize,
+                    [b] => *b as usize,
+                    _ => unreachable!(),
+                })
+                .collect()
+        }
+    };
+
+    if samples.iter().any(|s| *s > max_value) {
+        return Err(invalid_data("PPM: color value exceeds maximum."));
+    }
+
+    let scale = max_value as f64;
+    let pixels: Vec<Color> = samples
+        .chunks_exact(3)
+        .map(|c| {
+            Color::new(
+                c[0] as f64 / scale,
+                c[1] as f64 / scale,
+                c[2] as f64 / scale,
+            )
+        })
+        .collect();
+
+    Ok(Canvas::from_pixels(width, height, pixels))
+}
+
+/// Splits a Netpbm style header into whitespace separated tokens,
+/// skipping comments.
+pub(super) struct HeaderParser<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> HeaderParser<'a> {
+    pub(super) fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    pub(super) fn next_token(&mut self) -> io::Result<&'a [u8]> {
+        loop {
+            match self.data.get(self.pos) {
+                Some(b'#') => {
+                    while self.data.get(self.pos).is_some_and(|b| *b != b'\n') {
+                        self.pos += 1;
+                    }
+                }
+                Some(b) if b.is_ascii_whitespace() => self.pos += 1,
+                Some(_) => break,
+                None => return Err(invalid_data("Error: unexpected end of image header.")),
+            }
+        }
+        let start = self.pos;
+        while self
+            .data
+            .get(self.pos)
+            .is_some_and(|b| !b.is_ascii_whitespace() && *b != b'#')
+        {
+            self.pos += 1;
+        }
+        Ok(&self.data[start..self.pos])
+    }
+
+    pub(super) fn next_number<T: std::str::FromStr>(&mut self) -> io::Result<T> {
+        let token = self.next_token()?;
+        std::str::from_utf8(token)
+            .ok()
+            .and_then(|s| s.parse::<T>().ok())
+            .ok_or_else(|| invalid_data("Error: expected a number in image header."))
+    }
+
+    /// Returns the bytes after the single whitespace byte that terminates
+    /// the header of a binary image.
+    pub(super) fn raster(&self) -> io::Result<&'a [u8]> {
+        if !self.data.get(self.pos).is_some_and(u8::is_ascii_whitespace) {
+            return Err(invalid_data(
+                "Error: missing whitespace after image header.",
+            ));
+        }
+        Ok(&self.data[self.pos + 1..])
+    }
+}
+
+pub(super) fn invalid_data(msg: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scene::canvas::DisplayTransform;
+
+    fn encode(canvas: &Canvas, format: PpmFormat) -> Vec<u8> {
+        let mut out: Vec<u8> = Vec::new();
+        write_ppm(canvas, format, &mut out).unwrap();
+        out
+    }
+
+    #[test]
+    fn test_write_ppm_header() {
+        let canvas = Canvas::new(5, 3);
+        let out = String::from_utf8(encode(&canvas, PpmFormat::Ascii)).unwrap();
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines[0], "P3");
+        assert_eq!(lines[1], "5 3");
+        assert_eq!(lines[2], "255");
+    }
+
+    #[test]
+    fn test_write_ppm_ascii_pixels() {
+        let mut canvas = Canvas::new(5, 3);
+        canvas.set_display_transform(DisplayTransform::linear());
+        canvas.add_pixel(0, 0, Color::new(1.5, 0.0, 0.0));
+        canvas.add_pixel(2, 1, Color::new(0.0, 0.5, 0.0));
+        canvas.add_pixel(4, 2, Color::new(-0.5, 0.0, 1.0));
+        let out = String::from_utf8(encode(&canvas, PpmFormat::Ascii)).unwrap();
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines[3], "255 0 0 0 0 0 0 0 0 0 0 0 0 0 0");
+        assert_eq!(lines[4], "0 0 0 0 0 0 0 128 0 0 0 0 0 0 0");
+        assert_eq!(lines[5], "0 0 0 0 0 0 0 0 0 0 0 0 0 0 255");
+        assert!(out.ends_with('\n'));
+    }
+
+    #[test]
+    fn test_write_ppm_ascii_line_wrapping() {
+        let mut canvas = Canvas::new_with_color(10, 2, Color::new(1.0, 0.8, 0.6));
+        canvas.set_display_transform(DisplayTransform::linear());
+        let out = String::from_utf8(encode(&canvas, PpmFormat::Ascii)).unwrap();
+        let lines: Vec<&str> = out.lines().collect();
+        let first = "255 204 153 255 204 153 255 204 153 255 204 153 255 204 153 255 204";
+        let second = "153 255 204 153 255 204 153 255 204 153 255 204 153";
+        assert_eq!(lines[3..7], [first, second, first, second]);
+        assert!(lines.iter().all(|line| line.len() <= RENDER_COLUMN_MAX));
+    }
+
+    #[test]
+    fn test_write_ppm_binary() {
+        let mut canvas = Canvas::new(2, 1);
+        canvas.set_display_transform(DisplayTransform::linear());
+        canvas.add_pixel(1, 0, Color::new(1.0, 0.8, 0.6));
+        let out = encode(&canvas, PpmFormat::Binary);
+        let mut expected = b"P6\n2 1\n255\n".to_vec();
+        expected.extend_from_slice(&[0, 0, 0, 255, 204, 153]);
+        assert_eq!(out, expected);
+    }
+
+    #[test]
+    fn test_read_ppm_round_trip() {
+        let mut canvas = Canvas::new(4, 3);
+        canvas.set_display_transform(DisplayTransform::linear());
+        canvas.add_pixel(0, 0, Color::new(1.0, 0.0, 0.0));
+        canvas.add_pixel(3, 2, Color::new(0.2, 0.4, 0.6));
+        for format in [PpmFormat::Ascii, PpmFormat::Binary] {
+            let res = read_ppm(encode(&canvas, format).as_slice()).unwrap();
+            assert_eq!(res.width, 4);
+            assert_eq!(res.height, 3);
+            assert_eq!(res.get_pixel(0, 0), Some(Color::new(1.0, 0.0, 0.0)));
+            assert_eq!(res.get_pixel(1, 1), Some(Color::new_black()));
+            assert_eq!(
+                res.get_pixel(3, 2),
+                Some(Color::new(51.0 / 255.0, 102.0 / 255.0, 153.0 / 255.0))
+            );
+        }
+    }
+
+    #[test]
+    fn test_read_ppm_with_comments() {
+        let input = b"P3\n# created by hand\n2 # width\n1\n# max\n10\n10 0 5 # red\n0 10 0\n";
+        let res = read_ppm(&input[..]).unwrap();
+        assert_eq!(res.width, 2);
+        assert_eq!(res.height, 1);
+        assert_eq!(res.get_pixel(0, 0), Some(Color::new(1.0, 0.0, 0.5)));
+        assert_eq!(res.get_pixel(1, 0), Some(Color::new(0.0, 1.0, 0.0)));
+
+        let mut input = b"P6 # raw\n1 1 255\n".to_vec();
+        input.extend_from_slice(&[255, 0, 51]);
+        let res = read_ppm(input.as_slice()).unwrap();
+        assert_eq!(res.get_pixel(0, 0), Some(Color::new(1.0, 0.0, 0.2)));
+    }
+
+    #[test]
+    fn test_read_ppm_binary_16_bit() {
+        let mut input = b"P6\n1 1\n65535\n".to_vec();
+        input.extend_from_slice(&[0xff, 0xff, 0x00, 0x00, 0x80, 0x00]);
+        let res = read_ppm(input.as_slice()).unwrap();
+        assert_eq!(
+            res.get_pixel(0, 0),
+            Some(Color::new(1.0, 0.0, 32768.0 / 65535.0))
+        );
+    }
+
+    #[test]
+    fn test_read_ppm_yields_error() {
+        let inputs: [&[u8]; 9] = [
+            b"P5\n1 1\n255\n0",
+            b"P3\n1 1\n0\n0 0 0",
+            b"P3\n1 1\n255\n0 0",
+            b"P3\n1 1\n255\n0 0 256",
+            b"P3\n1 x\n255\n0 0 0",
+            b"P6\n2 1\n255\n\x00\x00\x00",
+            b"P6\n100000 100000\n255\n",
+            b"P3\n100000 100000\n255\n0 0 0",
+            b"P6\n18446744073709551615 2\n255\n",
+        ];
+        for input in inputs {
+            let err = read_ppm(input).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        }
+    }
+}
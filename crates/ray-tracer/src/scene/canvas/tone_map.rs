@@ -0,0 +1,296 @@
+//! # Tone Mapping Module
+//!
+//! The display transform that turns unbounded linear radiance into values
+//! for 8-bit and 16-bit images: exposure, a tone operator, optional sRGB
+//! encoding and optional ordered dithering.
+//!
+//! ## Features
+//! - Exposure adjustment in photographic stops.
+//! - Clamp, Reinhard, filmic (Hable) and ACES tone operators.
+//! - Linear to sRGB transfer function.
+//! - 4x4 Bayer dithering to hide banding in smooth gradients.
+
+use crate::shape::Color;
+
+/// Curve parameters of the Hable (Uncharted 2) filmic operator.
+const FILMIC_SHOULDER: f64 = 0.15;
+const FILMIC_LINEAR: f64 = 0.50;
+const FILMIC_ANGLE: f64 = 0.10;
+const FILMIC_TOE: f64 = 0.20;
+const FILMIC_TOE_NUMERATOR: f64 = 0.02;
+const FILMIC_TOE_DENOMINATOR: f64 = 0.30;
+const FILMIC_WHITE: f64 = 11.2;
+
+/// Normalized 4x4 Bayer matrix used for ordered dithering.
+const BAYER_4X4: [[f64; 4]; 4] = [
+    [0.0, 8.0, 2.0, 10.0],
+    [12.0, 4.0, 14.0, 6.0],
+    [3.0, 11.0, 1.0, 9.0],
+    [15.0, 7.0, 13.0, 5.0],
+];
+
+/// Maps scene-referred linear values into the display range `0.0..=1.0`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ToneOperator {
+    /// Clips values above `1.0`.
+    Clamp,
+    /// `x / (1 + x)`: never clips, but desaturates highlights.
+    Reinhard,
+    /// John Hable's filmic curve with a white point of `11.2`.
+    Filmic,
+    /// Krzysztof Narkowicz's fit of the ACES reference rendering transform.
+    Aces,
+}
+
+impl ToneOperator {
+    /// Applies the operator to a single non-negative channel value.
+    pub fn map(&self, x: f64) -> f64 {
+        let x = x.max(0.0);
+        let mapped = match self {
+            ToneOperator::Clamp => x,
+            ToneOperator::Reinhard => x / (1.0 + x),
+            ToneOperator::Filmic => hable(x) / hable(FILMIC_WHITE),
+            ToneOperator::Aces => (x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14),
+        };
+        mapped.clamp(0.0, 1.0)
+    }
+}
+
+fn hable(x: f64) -> f64 {
+    let (a, b, c, d) = (FILMIC_SHOULDER, FILMIC_LINEAR, FILMIC_ANGLE, FILMIC_TOE);
+    let (e, f) = (FILMIC_TOE_NUMERATOR, FILMIC_TOE_DENOMINATOR);
+    ((x * (a * x + c * b) + d * e) / (x * (a * x + b) + d * f)) - e / f
+}
+
+/// Encodes a linear value in `0.0..=1.0` with the sRGB transfer function.
+pub fn linear_to_srgb(value: f64) -> f64 {
+    let value = value.clamp(0.0, 1.0);
+    if value <= 0.0031308 {
+        value * 12.92
+    } else if value >= 1.0 {
+        // avoid 0.99999... from the power curve so white stays at 255
+        1.0
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// The display transform applied by every 8-bit and 16-bit exporter.
+///
+/// The default encodes for a standard sRGB display: no exposure change, a
+/// hard clamp and the sRGB transfer function. `DisplayTransform::linear`
+/// reproduces the book's output, without gamma encoding.
+///
+/// ## Fields
+/// - `exposure`: Exposure adjustment in stops; each stop doubles brightness.
+/// - `operator`: The tone operator applied after exposure.
+/// - `srgb`: Whether to apply the sRGB transfer function.
+/// - `dither`: Whether to add ordered dithering before quantization.
+///   Without it, values are rounded to the nearest level.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DisplayTransform {
+    pub exposure: f64,
+    pub operator: ToneOperator,
+    pub srgb: bool,
+    pub dither: bool,
+}
+
+impl Default for DisplayTransform {
+    fn default() -> Self {
+        DisplayTransform::new()
+    }
+}
+
+impl DisplayTransform {
+    /// Creates the default transform: a hard clamp, encoded for a
+    /// standard sRGB display.
+    pub fn new() -> Self {
+        DisplayTransform::srgb(ToneOperator::Clamp)
+    }
+
+    /// Creates the linear, clamping transform used by the book.
+    pub fn linear() -> Self {
+        Self {
+            exposure: 0.0,
+            operator: ToneOperator::Clamp,
+            srgb: false,
+            dither: false,
+        }
+    }
+
+    /// Creates a transform for viewing on a standard sRGB display.
+    pub fn srgb(operator: ToneOperator) -> Self {
+        Self {
+            exposure: 0.0,
+            operator,
+            srgb: true,
+            dither: false,
+        }
+    }
+}
+
+impl DisplayTransform {
+    /// Applies exposure, tone mapping and encoding, returning display values
+    /// in `0.0..=1.0`.
+    pub fn apply(&self, color: Color) -> Color {
+        let gain = 2f64.powf(self.exposure);
+        let encode = |v: f64| {
+            let mapped = self.operator.map(v * gain);
+            if self.srgb {
+                linear_to_srgb(mapped)
+            } else {
+                mapped
+            }
+        };
+        Color::new(
+            encode(color.get_red()),
+            encode(color.get_green()),
+            encode(color.get_blue()),
+        )
+    }
+
+    /// Converts a color at pixel `(x, y)` to 8-bit channels.
+    ///
+    /// The pixel position only matters when dithering is enabled.
+    pub fn to_u8(&self, color: Color, x: usize, y: usize) -> [u8; 3] {
+        let display = self.apply(color);
+        let offset = self.dither_offset(x, y);
+        let quantize = |v: f64| (v * 255.0 + offset).floor().clamp(0.0, 255.0) as u8;
+        [
+            quantize(display.get_red()),
+            quantize(display.get_green()),
+            quantize(display.get_blue()),
+        ]
+    }
+
+    /// Converts a color to 16-bit channels.
+    ///
+    /// Dithering is never applied at this depth.
+    pub fn to_u16(&self, color: Color) -> [u16; 3] {
+        let display = self.apply(color);
+        let quantize = |v: f64| (v * 65535.0).round() as u16;
+        [
+            quantize(display.get_red()),
+            quantize(display.get_green()),
+            quantize(display.get_blue()),
+        ]
+    }
+
+    /// Returns the threshold in `0.0..1.0` added before truncation: the
+    /// ordered dither pattern, or `0.5` to round to the nearest value.
+    fn dither_offset(&self, x: usize, y: usize) -> f64 {
+        if self.dither {
+            (BAYER_4X4[y % 4][x % 4] + 0.5) / 16.0
+        } else {
+            0.5
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::approx_eq;
+
+    #[test]
+    fn test_tone_operators() {
+        assert_eq!(ToneOperator::Clamp.map(0.5), 0.5);
+        assert_eq!(ToneOperator::Clamp.map(3.0), 1.0);
+        assert_eq!(ToneOperator::Clamp.map(-1.0), 0.0);
+        assert_eq!(ToneOperator::Reinhard.map(1.0), 0.5);
+        assert_eq!(ToneOperator::Reinhard.map(3.0), 0.75);
+        assert!(approx_eq(ToneOperator::Filmic.map(FILMIC_WHITE), 1.0));
+        assert_eq!(ToneOperator::Filmic.map(0.0), 0.0);
+        assert_eq!(ToneOperator::Aces.map(0.0), 0.0);
+        assert_eq!(ToneOperator::Aces.map(100.0), 1.0);
+
+        for op in [
+            ToneOperator::Reinhard,
+            ToneOperator::Filmic,
+            ToneOperator::Aces,
+        ] {
+            assert!(op.map(0.5) < op.map(1.0));
+            assert!(op.map(1.0) < op.map(4.0));
+        }
+    }
+
+    #[test]
+    fn test_linear_to_srgb() {
+        assert_eq!(linear_to_srgb(0.0), 0.0);
+        assert!(approx_eq(linear_to_srgb(1.0), 1.0));
+        assert!(approx_eq(linear_to_srgb(0.002), 0.02584));
+        assert!(approx_eq(linear_to_srgb(0.5), 0.73536));
+        assert!(approx_eq(linear_to_srgb(2.0), 1.0));
+    }
+
+    #[test]
+    fn test_linear_rounds_u8() {
+        let transform = DisplayTransform::linear();
+        assert_eq!(
+            transform.to_u8(Color::new(1.0, 0.8, 0.6), 0, 0),
+            [255, 204, 153]
+        );
+        assert_eq!(
+            transform.to_u8(Color::new(1.5, -0.5, 0.5), 3, 7),
+            [255, 0, 128]
+        );
+        // values just below a level round up to it, like 16-bit output
+        let px = Color::new(99.999 / 255.0, 0.499 / 255.0, 254.6 / 255.0);
+        assert_eq!(transform.to_u8(px, 0, 0), [100, 0, 255]);
+        assert_eq!(
+            transform.to_u16(Color::new(0.999 / 65535.0, 0.0, 1.0)),
+            [1, 0, 65535]
+        );
+    }
+
+    #[test]
+    fn test_exposure() {
+        let mut transform = DisplayTransform::linear();
+        transform.exposure = 1.0;
+        assert_eq!(
+            transform.apply(Color::new(0.25, 0.5, 1.0)),
+            Color::new(0.5, 1.0, 1.0)
+        );
+        transform.exposure = -2.0;
+        assert_eq!(
+            transform.apply(Color::new(2.0, 1.0, 0.0)),
+            Color::new(0.5, 0.25, 0.0)
+        );
+    }
+
+    #[test]
+    fn test_srgb_transform() {
+        let transform = DisplayTransform::new();
+        assert_eq!(transform, DisplayTransform::srgb(ToneOperator::Clamp));
+        assert_eq!(DisplayTransform::default(), transform);
+        assert_eq!(
+            transform.to_u8(Color::new(0.5, 0.0, 1.0), 0, 0),
+            [188, 0, 255]
+        );
+        assert_eq!(
+            transform.to_u16(Color::new(0.5, 0.0, 1.0)),
+            [48192, 0, 65535]
+        );
+    }
+
+    #[test]
+    fn test_dither() {
+        let mut transform = DisplayTransform::linear();
+        transform.dither = true;
+        // a value halfway between two levels rounds up in about half the pixels
+        let value = Color::new(100.5 / 255.0, 0.0, 1.0);
+        let mut up = 0;
+        for y in 0..4 {
+            for x in 0..4 {
+                let [r, g, b] = transform.to_u8(value, x, y);
+                assert!(r == 100 || r == 101);
+                assert_eq!(g, 0);
+                assert_eq!(b, 255);
+                if r == 101 {
+                    up += 1;
+                }
+            }
+        }
+        assert_eq!(up, 8);
+    }
+}
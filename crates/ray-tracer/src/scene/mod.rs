@@ -1,7 +1,9 @@
 pub mod camera;
 pub mod canvas;
+pub mod render;
 pub mod world;
 
 pub use camera::Camera;
 pub use canvas::Canvas;
+pub use render::{RenderSettings, Tile};
 pub use world::{Comps, World};
@@ -0,0 +1,296 @@
+//! Render Module
+//!
+//! Renders a world through a camera into a canvas, tracing several jittered
+//! samples per pixel on several threads, or tile by tile for callers that
+//! schedule the work themselves.
+
+use crate::scene::world::DEFAULT_MAX_DEPTH;
+use crate::scene::{Camera, Canvas, World};
+use crate::shape::Color;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+/// The settings of a render.
+///
+/// ## Fields
+/// - `width`: The width of the image in pixels.
+/// - `height`: The height of the image in pixels.
+/// - `samples`: The rays traced per pixel. A single ray goes through the
+///   pixel center; more rays are jittered over a grid covering the pixel
+///   and averaged, which smooths edges.
+/// - `max_depth`: The longest chain of secondary rays, such as reflections,
+///   traced from a camera ray. It is passed to `World::color_at_depth`,
+///   which does not trace secondary rays yet.
+/// - `threads`: The threads rendering rows, or `0` for one per core.
+/// - `seed`: Seeds the jitter of the samples. A seed always renders the
+///   same image, whatever the number of threads.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RenderSettings {
+    pub width: usize,
+    pub height: usize,
+    pub samples: u32,
+    pub max_depth: u32,
+    pub threads: usize,
+    pub seed: u64,
+}
+
+impl Default for RenderSettings {
+    /// A 640 by 480 image with one sample per pixel, on every core.
+    fn default() -> Self {
+        Self {
+            width: 640,
+            height: 480,
+            samples: 1,
+            max_depth: DEFAULT_MAX_DEPTH,
+            threads: 0,
+            seed: 0,
+        }
+    }
+}
+
+impl RenderSettings {
+    /// Returns the number of threads to render with.
+    pub fn thread_count(&self) -> usize {
+        match self.threads {
+            0 => thread::available_parallelism().map_or(1, |n| n.get()),
+            n => n,
+        }
+    }
+}
+
+/// A rectangle of pixels, measured from the top left corner of the image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Tile {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl RenderSettings {
+    /// Splits the image into tiles of at most `size` by `size` pixels, row
+    /// by row from the top left. Together they cover every pixel once.
+    pub fn tiles(&self, size: usize) -> Vec<Tile> {
+        let size = size.max(1);
+        let mut tiles = Vec::new();
+        for y in (0..self.height).step_by(size) {
+            for x in (0..self.width).step_by(size) {
+                tiles.push(Tile {
+                    x,
+                    y,
+                    width: size.min(self.width - x),
+                    height: size.min(self.height - y),
+                });
+            }
+        }
+        tiles
+    }
+}
+
+impl Camera {
+    /// Renders `world` into a new canvas as described by `settings`.
+    pub fn render_with(&self, world: &World, settings: &RenderSettings) -> Canvas {
+        let (width, height) = (settings.width, settings.height);
+        let next_row = AtomicUsize::new(0);
+        let threads = settings.thread_count().clamp(1, height.max(1));
+
+        let mut rows: Vec<(usize, Vec<Color>)> = thread::scope(|scope| {
+            let workers: Vec<_> = (0..threads)
+                .map(|_| {
+                    scope.spawn(|| {
+                        let mut rows = Vec::new();
+                        loop {
+                            let y = next_row.fetch_add(1, Ordering::Relaxed);
+                            if y >= height {
+                                return rows;
+                            }
+                            let row = (0..width)
+                                .map(|x| self.render_pixel(world, x, y, settings))
+                                .collect();
+                            rows.push((y, row));
+                        }
+                    })
+                })
+                .collect();
+            workers
+                .into_iter()
+                .flat_map(|worker| worker.join().expect("a render thread panicked"))
+                .collect()
+        });
+
+        rows.sort_unstable_by_key(|(y, _)| *y);
+        let pixels = rows.into_iter().flat_map(|(_, row)| row).collect();
+        Canvas::from_pixels(width, height, pixels)
+    }
+
+    /// Renders the pixels of `tile` in row-major order, exactly as
+    /// `render_with` renders them, so an image can be shown as its tiles
+    /// complete.
+    pub fn render_tile(&self, world: &World, tile: Tile, settings: &RenderSettings) -> Vec<Color> {
+        (tile.y..tile.y + tile.height)
+            .flat_map(|y| (tile.x..tile.x + tile.width).map(move |x| (x, y)))
+            .map(|(x, y)| self.render_pixel(world, x, y, settings))
+            .collect()
+    }
+
+    /// Returns the color of pixel `(x, y)`: the average of its samples.
+    pub fn render_pixel(
+        &self,
+        world: &World,
+        x: usize,
+        y: usize,
+        settings: &RenderSettings,
+    ) -> Color {
+        let (width, height) = (settings.width, settings.height);
+        if settings.samples <= 1 {
+            let ray = self.ray_for_pixel(x, y, width, height);
+            return world.color_at_depth(ray, settings.max_depth);
+        }
+
+        // the smallest square grid with a cell for every sample
+        let grid = (settings.samples as f64).sqrt().ceil() as u32;
+        let pixel = (y * width + x) as u64;
+        let mut rng = SplitMix64(settings.seed ^ pixel.wrapping_mul(0x9e37_79b9_7f4a_7c15));
+        let mut sum = Color::new_black();
+        for sample in 0..settings.samples {
+            let cell_x = (sample % grid) as f64 + rng.next_f64();
+            let cell_y = (sample / grid) as f64 + rng.next_f64();
+            let ray = self.ray_through(
+                x as f64 + cell_x / grid as f64,
+                y as f64 + cell_y / grid as f64,
+                width,
+                height,
+            );
+            sum = sum + world.color_at_depth(ray, settings.max_depth);
+        }
+        sum * (1.0 / settings.samples as f64)
+    }
+}
+
+/// The SplitMix64 generator: tiny, fast and good enough to jitter samples.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Returns a number in `0.0..1.0`.
+    fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::Tuple;
+
+    fn camera() -> Camera {
+        Camera::new(
+            Tuple::point(0.0, 0.0, -5.0),
+            Tuple::point(0.0, 0.0, 0.0),
+            60.0,
+        )
+    }
+
+    #[test]
+    fn render_with_ok() {
+        let world = World::new();
+        let single = RenderSettings {
+            width: 16,
+            height: 12,
+            threads: 1,
+            ..Default::default()
+        };
+        let threaded = RenderSettings {
+            threads: 3,
+            ..single
+        };
+        let a = camera().render_with(&world, &single);
+        let b = camera().render_with(&world, &threaded);
+        assert_eq!((a.width, a.height), (16, 12));
+        for y in 0..12 {
+            for x in 0..16 {
+                assert_eq!(a.get_pixel(x, y), b.get_pixel(x, y));
+            }
+        }
+        assert_eq!(
+            a.get_pixel(8, 6),
+            Some(world.color_at(camera().ray_for_pixel(8, 6, 16, 12)))
+        );
+    }
+
+    #[test]
+    fn tiles_ok() {
+        let settings = RenderSettings {
+            width: 5,
+            height: 3,
+            ..Default::default()
+        };
+        let tiles = settings.tiles(2);
+        assert_eq!(tiles.len(), 6);
+        assert_eq!(
+            tiles[2],
+            Tile {
+                x: 4,
+                y: 0,
+                width: 1,
+                height: 2
+            }
+        );
+        let pixels: usize = tiles.iter().map(|t| t.width * t.height).sum();
+        assert_eq!(pixels, 15);
+        assert_eq!(settings.tiles(0).len(), 15);
+    }
+
+    #[test]
+    fn render_tile_ok() {
+        let world = World::new();
+        let settings = RenderSettings {
+            width: 16,
+            height: 12,
+            samples: 4,
+            ..Default::default()
+        };
+        let canvas = camera().render_with(&world, &settings);
+        for tile in settings.tiles(5) {
+            let colors = camera().render_tile(&world, tile, &settings);
+            assert_eq!(colors.len(), tile.width * tile.height);
+            let last = (tile.x + tile.width - 1, tile.y + tile.height - 1);
+            assert_eq!(canvas.get_pixel(tile.x, tile.y), Some(colors[0]));
+            assert_eq!(canvas.get_pixel(last.0, last.1), colors.last().copied());
+        }
+    }
+
+    #[test]
+    fn render_pixel_ok() {
+        let world = World::new();
+        let settings = RenderSettings {
+            width: 16,
+            height: 12,
+            samples: 16,
+            seed: 7,
+            ..Default::default()
+        };
+        let camera = camera();
+        // the same seed renders the same samples
+        let edge = camera.render_pixel(&world, 5, 6, &settings);
+        assert_eq!(edge, camera.render_pixel(&world, 5, 6, &settings));
+        let center = camera.render_pixel(&world, 8, 6, &settings);
+        let background = camera.render_pixel(&world, 0, 0, &settings);
+        assert_eq!(background, Color::new_black());
+        assert_ne!(center, Color::new_black());
+
+        let mut rng = SplitMix64(7);
+        assert!(
+            (0..1000)
+                .map(|_| rng.next_f64())
+                .all(|v| (0.0..1.0).contains(&v))
+        );
+    }
+}
@@ -5,6 +5,9 @@ use crate::math::{Matrix, Ray, Tuple};
 use crate::shape::{Color, Shape, Sphere};
 use crate::shape::{Intersection, Intersections};
 
+/// The depth of secondary rays traced by `World::color_at`, as in the book.
+pub const DEFAULT_MAX_DEPTH: u32 = 5;
+
 #[derive(Debug)]
 pub struct World {
     pub objects: Vec<Shape>,
@@ -89,7 +92,17 @@ impl World {
         )
     }
 
+    /// Returns the color seen along `ray`, with the default depth of
+    /// secondary rays.
     pub fn color_at(&self, ray: Ray) -> Color {
+        self.color_at_depth(ray, DEFAULT_MAX_DEPTH)
+    }
+
+    /// Returns the color seen along `ray`, tracing at most `remaining`
+    /// secondary rays, such as reflections, from its hit.
+    pub fn color_at_depth(&self, ray: Ray, remaining: u32) -> Color {
+        // no material traces secondary rays yet, so there is nothing to limit
+        let _ = remaining;
         let collection = self.intersect_world(ray);
         let closest_hit = collection.hit();
         if closest_hit.is_none() {
@@ -109,14 +122,18 @@ mod test {
         let w = World::new();
         assert_eq!(w.objects.len(), 2);
 
-        let Shape::Sphere(s1) = w.objects.first().unwrap();
+        let Some(Shape::Sphere(s1)) = w.objects.first() else {
+            panic!("expected a sphere");
+        };
         assert_eq!(s1.origin, Tuple::point(0.0, 0.0, 0.0));
         assert_eq!(s1.radius, 1.0);
         assert_eq!(s1.material.color, Color::new(0.8, 1.0, 0.6));
         assert_eq!(s1.material.diffuse, 0.7);
         assert_eq!(s1.material.specular, 0.2);
 
-        let Shape::Sphere(s2) = w.objects.get(1).unwrap();
+        let Some(Shape::Sphere(s2)) = w.objects.get(1) else {
+            panic!("expected a sphere");
+        };
         assert_eq!(s2.origin, Tuple::point(0.0, 0.0, 0.0));
         assert_eq!(s2.radius, 1.0);
         assert_eq!(s2.transform, Matrix::scaling(0.5, 0.5, 0.5));
@@ -170,4 +187,14 @@ mod test {
         let color = world.color_at(ray);
         assert_eq!(color, inner_color);
     }
+
+    #[test]
+    fn test_color_at_depth() {
+        let world = World::new();
+        let ray = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+        // without reflective materials the depth only bounds future rays
+        for remaining in [0, 1, DEFAULT_MAX_DEPTH] {
+            assert_eq!(world.color_at_depth(ray, remaining), world.color_at(ray));
+        }
+    }
 }
@@ -25,6 +25,18 @@ impl Color {
 }
 
 impl Color {
+    pub fn get_red(&self) -> f64 {
+        self.tuple.x
+    }
+
+    pub fn get_green(&self) -> f64 {
+        self.tuple.y
+    }
+
+    pub fn get_blue(&self) -> f64 {
+        self.tuple.z
+    }
+
     pub fn get_clamped_red_u8(&self) -> u8 {
         (self.tuple.x.clamp(0.0, 1.0) * 255.0).floor() as u8
     }
@@ -161,6 +173,15 @@ mod tests {
         assert_eq!(a * b, res);
     }
 
+    #[test]
+    fn test_get_channels() {
+        let px = Color::new(1.5, -0.2, 0.6);
+
+        assert_eq!(px.get_red(), 1.5);
+        assert_eq!(px.get_green(), -0.2);
+        assert_eq!(px.get_blue(), 0.6);
+    }
+
     #[test]
     fn test_get_clamped_u8() {
         let px = Color::new(1.0, 0.8, 0.6);
@@ -1,11 +1,14 @@
 pub mod color;
 pub mod intersection;
 pub mod material;
+pub mod plane;
+#[allow(clippy::module_inception)]
 pub mod shape;
 pub mod sphere;
 
 pub use color::Color;
 pub use intersection::{Intersection, Intersections};
 pub use material::Material;
+pub use plane::Plane;
 pub use shape::Shape;
 pub use sphere::Sphere;
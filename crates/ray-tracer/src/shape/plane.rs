@@ -0,0 +1,111 @@
+//! Planes Module
+
+use crate::math::{EPSILON, Matrix, Ray, Tuple};
+use crate::shape::Material;
+
+/// An infinite plane, the xz plane of its object space.
+#[derive(Debug, Clone)]
+pub struct Plane {
+    pub transform: Matrix<4>,
+    pub material: Material,
+}
+
+impl Default for Plane {
+    fn default() -> Self {
+        Plane::new()
+    }
+}
+impl Plane {
+    /// Constructs a new plane with default fields
+    pub fn new() -> Self {
+        Plane {
+            transform: Matrix::identity(),
+            material: Material::new(),
+        }
+    }
+
+    /// Allows a transformation to be assigned to a plane.
+    pub fn set_transformation(&mut self, transformation: Matrix<4>) {
+        self.transform = transformation;
+    }
+
+    pub fn set_material(&mut self, material: Material) {
+        self.material = material;
+    }
+}
+
+impl Plane {
+    /// Determines where a given ray intersects the plane, if at all.
+    /// A ray parallel to the plane, including one inside it, misses.
+    pub fn intersect(&self, ray: Ray) -> Vec<f64> {
+        let inverse_transform = self
+            .transform
+            .inverse()
+            .expect("Plane transform must be invertible");
+        let ray = ray.transform(inverse_transform);
+        if ray.direction.y.abs() < EPSILON {
+            return vec![];
+        }
+        vec![-ray.origin.y / ray.direction.y]
+    }
+
+    /// The normal is the same everywhere on the plane.
+    pub fn normal_at(&self, world_point: Tuple) -> Tuple {
+        assert!(world_point.is_point(), "Plane normal takes a point.");
+        let inv_transform = self
+            .transform
+            .inverse()
+            .expect("normal_at(): Could not inverse matrix.");
+        let object_normal = Tuple::vector(0.0, 1.0, 0.0);
+        let mut world_normal = inv_transform.transpose() * object_normal;
+        world_normal.w = 0.0;
+        world_normal.normalize()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::f64::consts::{FRAC_1_SQRT_2, FRAC_PI_4};
+
+    #[test]
+    fn test_normal_at() {
+        let p = Plane::new();
+        for point in [
+            Tuple::point(0.0, 0.0, 0.0),
+            Tuple::point(10.0, 0.0, -10.0),
+            Tuple::point(-5.0, 0.0, 150.0),
+        ] {
+            assert_eq!(p.normal_at(point), Tuple::vector(0.0, 1.0, 0.0));
+        }
+    }
+
+    #[test]
+    fn test_intersect() {
+        let p = Plane::new();
+        let ray = Ray::new(Tuple::point(0.0, 10.0, 0.0), Tuple::vector(0.0, 0.0, 1.0));
+        assert!(p.intersect(ray).is_empty());
+        let ray = Ray::new(Tuple::point(0.0, 0.0, 0.0), Tuple::vector(0.0, 0.0, 1.0));
+        assert!(p.intersect(ray).is_empty());
+        let ray = Ray::new(Tuple::point(0.0, 1.0, 0.0), Tuple::vector(0.0, -1.0, 0.0));
+        assert_eq!(p.intersect(ray), [1.0]);
+        let ray = Ray::new(Tuple::point(0.0, -1.0, 0.0), Tuple::vector(0.0, 1.0, 0.0));
+        assert_eq!(p.intersect(ray), [1.0]);
+    }
+
+    #[test]
+    fn test_intersect_with_transformation() {
+        let mut p = Plane::new();
+        p.set_transformation(Matrix::translation(0.0, -2.0, 0.0));
+        let ray = Ray::new(Tuple::point(0.0, 1.0, 0.0), Tuple::vector(0.0, -1.0, 0.0));
+        assert_eq!(p.intersect(ray), [3.0]);
+    }
+
+    #[test]
+    fn test_normal_at_with_transformation() {
+        let mut p = Plane::new();
+        p.set_transformation(Matrix::rotation_x(FRAC_PI_4));
+        let res = p.normal_at(Tuple::point(0.0, 0.0, 0.0));
+        assert_eq!(res, Tuple::vector(0.0, FRAC_1_SQRT_2, FRAC_1_SQRT_2));
+    }
+}
@@ -3,29 +3,33 @@
 //!
 
 use crate::math::{Ray, Tuple};
-use crate::shape::{Material, Sphere};
+use crate::shape::{Material, Plane, Sphere};
 
 #[derive(Debug, Clone)]
 pub enum Shape {
     Sphere(Sphere),
+    Plane(Plane),
 }
 
 impl Shape {
     pub fn intersect(&self, ray: Ray) -> Vec<f64> {
         match self {
             Shape::Sphere(sp) => sp.intersect(ray),
+            Shape::Plane(p) => p.intersect(ray),
         }
     }
 
     pub fn normal_at(&self, point: Tuple) -> Tuple {
         match self {
             Shape::Sphere(sp) => sp.normal_at(point),
+            Shape::Plane(p) => p.normal_at(point),
         }
     }
 
     pub fn get_material(&self) -> &Material {
         match self {
             Shape::Sphere(sp) => &sp.material,
+            Shape::Plane(p) => &p.material,
         }
     }
 }
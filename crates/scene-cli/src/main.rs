@@ -0,0 +1,52 @@
+mod render;
+
+use clap::{Parser, Subcommand};
+use std::fs;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+/// Tools for scene files.
+#[derive(Parser)]
+#[command(version)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Print the JSON Schema of the scene format, for editors to complete
+    /// and check scene files with.
+    Schema {
+        /// Write the schema to this file instead of stdout.
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Render a scene file to an image, or an animated scene to an
+    /// animated image or a sequence of frames.
+    #[command(after_help = render::EXIT_CODES)]
+    Render(render::RenderArgs),
+}
+
+fn main() -> ExitCode {
+    match Cli::parse().command {
+        Command::Schema { output } => schema(output),
+        Command::Render(args) => render::render(args),
+    }
+}
+
+fn schema(output: Option<PathBuf>) -> ExitCode {
+    let mut text = serde_json::to_string_pretty(&scene_types::scene_schema())
+        .expect("a schema is always valid JSON");
+    text.push('\n');
+    match output {
+        None => print!("{text}"),
+        Some(path) => {
+            if let Err(err) = fs::write(&path, text) {
+                eprintln!("error: cannot write {}: {err}", path.display());
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+    ExitCode::SUCCESS
+}
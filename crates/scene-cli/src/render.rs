@@ -0,0 +1,467 @@
+use clap::{Args, ValueEnum};
+use ray_tracer::scene::RenderSettings;
+use ray_tracer::scene::canvas::{AnimationFormat, ImageFormat, PngFormat, PpmFormat};
+use scene_loader::animation::{render_animation_as, render_frame, render_frames};
+use scene_loader::cli::parse_resolution;
+use scene_loader::error::SceneError;
+use scene_loader::load_scene_with_warnings;
+use scene_loader::migrate::MigrationWarning;
+use scene_loader::watch::SceneWatcher;
+use scene_types::SceneFile;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::Duration;
+
+/// The exit code when the image was written.
+const EXIT_SUCCESS: u8 = 0;
+/// The exit code when the output cannot be written.
+const EXIT_OUTPUT: u8 = 1;
+/// The exit code of command line errors, as used by `clap`.
+const EXIT_USAGE: u8 = 2;
+/// The exit code when the scene file cannot be read.
+const EXIT_UNREADABLE: u8 = 3;
+/// The exit code when the scene file is not a well-formed scene.
+const EXIT_MALFORMED: u8 = 4;
+/// The exit code when the scene is well-formed but cannot be rendered.
+const EXIT_INVALID: u8 = 5;
+
+/// How often the files of a watched scene are looked at.
+const WATCH_INTERVAL: Duration = Duration::from_millis(200);
+
+pub const EXIT_CODES: &str = "\
+Exit codes:
+  0  the image was written
+  1  the output cannot be written
+  2  the command line is invalid
+  3  the scene file cannot be read
+  4  the scene file is not a well-formed scene
+  5  the scene cannot be rendered, e.g. it has a negative radius";
+
+#[derive(Args)]
+pub struct RenderArgs {
+    /// The scene file, in TOML, JSON or YAML.
+    scene: PathBuf,
+
+    /// The image to write. Defaults to the scene name with the extension of
+    /// the format, `.png` or `.gif` for animated scenes. Animated scenes
+    /// written to a `.png` file are animated PNGs. Animated scenes rendered
+    /// to a still image format write `frame_0001.png` and so on into this
+    /// directory instead, which defaults to the scene name.
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+
+    /// The output format. Defaults to the one of the output extension.
+    #[arg(short, long, value_enum)]
+    format: Option<OutputFormat>,
+
+    /// The image size in pixels, as WIDTHxHEIGHT.
+    #[arg(short, long, value_parser = parse_resolution, default_value = "640x480")]
+    resolution: (usize, usize),
+
+    /// The rays traced per pixel.
+    #[arg(short, long, default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..))]
+    spp: u32,
+
+    /// The longest chain of secondary rays, such as reflections.
+    #[arg(long, default_value_t = RenderSettings::default().max_depth)]
+    max_depth: u32,
+
+    /// The threads to render with, or 0 for one per core.
+    #[arg(short = 'j', long, default_value_t = 0)]
+    threads: usize,
+
+    /// Seeds the placement of the samples in each pixel.
+    #[arg(long, default_value_t = 0)]
+    seed: u64,
+
+    /// Do not dither GIF colors.
+    #[arg(long)]
+    no_dither: bool,
+
+    /// Keep running, and render again whenever the scene file or a file it
+    /// includes changes. When the scene fails to load, the error is printed
+    /// and the last image is kept.
+    #[arg(short, long)]
+    watch: bool,
+}
+
+/// The formats the renderer writes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// Binary PPM.
+    Ppm,
+    /// 8-bit RGB PNG.
+    Png,
+    /// 16-bit RGB PNG.
+    Png16,
+    /// Portable Float Map, unclamped.
+    Pfm,
+    /// Radiance RGBE, unclamped.
+    Hdr,
+    /// Animated GIF.
+    Gif,
+    /// Animated PNG.
+    Apng,
+}
+
+impl OutputFormat {
+    /// Selects a format from the extension of `path`. A `.png` file of an
+    /// animated scene is an animated PNG.
+    fn from_path(path: &Path, animated: bool) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "ppm" => Some(OutputFormat::Ppm),
+            "png" if animated => Some(OutputFormat::Apng),
+            "png" => Some(OutputFormat::Png),
+            "pfm" => Some(OutputFormat::Pfm),
+            "hdr" => Some(OutputFormat::Hdr),
+            "gif" => Some(OutputFormat::Gif),
+            "apng" => Some(OutputFormat::Apng),
+            _ => None,
+        }
+    }
+
+    /// Returns how frames are written: as separate images, or together as
+    /// one animated image.
+    fn encoding(self) -> Result<ImageFormat, AnimationFormat> {
+        match self {
+            OutputFormat::Ppm => Ok(ImageFormat::Ppm(PpmFormat::Binary)),
+            OutputFormat::Png => Ok(ImageFormat::Png(PngFormat::Rgb8)),
+            OutputFormat::Png16 => Ok(ImageFormat::Png(PngFormat::Rgb16)),
+            OutputFormat::Pfm => Ok(ImageFormat::Pfm),
+            OutputFormat::Hdr => Ok(ImageFormat::Hdr),
+            OutputFormat::Gif => Err(AnimationFormat::Gif),
+            OutputFormat::Apng => Err(AnimationFormat::Apng),
+        }
+    }
+
+    /// The file extension of the format.
+    fn extension(self) -> &'static str {
+        match self.encoding() {
+            Ok(image) => image.extension(),
+            Err(AnimationFormat::Gif) => "gif",
+            Err(AnimationFormat::Apng) => "png",
+        }
+    }
+}
+
+/// Renders a scene file as described by `args`, and prints the paths of
+/// the images written.
+pub fn render(args: RenderArgs) -> ExitCode {
+    if args.watch {
+        return watch(&args);
+    }
+    ExitCode::from(render_loaded(&args, load_scene_with_warnings(&args.scene)))
+}
+
+/// Renders the scene every time one of its files changes, until the command
+/// line turns out to be invalid.
+fn watch(args: &RenderArgs) -> ExitCode {
+    let mut watcher = SceneWatcher::new(&args.scene);
+    let mut rendered = false;
+    loop {
+        match render_loaded(args, watcher.load()) {
+            EXIT_SUCCESS => rendered = true,
+            EXIT_USAGE => return ExitCode::from(EXIT_USAGE),
+            _ if rendered => eprintln!("keeping the last image"),
+            _ => {}
+        }
+        let files: Vec<String> = watcher
+            .files()
+            .map(|path| path.display().to_string())
+            .collect();
+        eprintln!("watching {} for changes", files.join(", "));
+        watcher.wait(WATCH_INTERVAL);
+    }
+}
+
+/// Renders a loaded scene and writes the images, returning the exit code.
+/// Frames of a sequence are written as they render, and an animated image
+/// once every frame has rendered.
+fn render_loaded(
+    args: &RenderArgs,
+    loaded: Result<(SceneFile, Vec<MigrationWarning>), SceneError>,
+) -> u8 {
+    let scene = match loaded {
+        Ok((scene, warnings)) => {
+            for warning in warnings {
+                eprintln!("warning: {warning}");
+            }
+            scene
+        }
+        Err(err) => return scene_error(&args.scene, &err),
+    };
+    let animated = scene.animation.is_some();
+
+    let output = output_path(args, animated);
+    let Some(format) = args
+        .format
+        .or_else(|| OutputFormat::from_path(&output, animated))
+    else {
+        eprintln!(
+            "error: cannot tell the format of {}, use --format",
+            output.display()
+        );
+        return EXIT_USAGE;
+    };
+
+    let (width, height) = args.resolution;
+    let settings = RenderSettings {
+        width,
+        height,
+        samples: args.spp,
+        max_depth: args.max_depth,
+        threads: args.threads,
+        seed: args.seed,
+    };
+    let written = match format.encoding() {
+        Err(animation) => {
+            render_animation_as(&scene, &output, &settings, animation, !args.no_dither)
+                .map(|()| vec![output.clone()])
+        }
+        Ok(image) if animated => render_frames(&scene, &output, &settings, image),
+        Ok(image) => render_frame(&scene, 1, &settings).and_then(|canvas| {
+            canvas.save_as(&output, image)?;
+            Ok(vec![output.clone()])
+        }),
+    };
+    match written {
+        Ok(paths) => {
+            for path in paths {
+                println!("{}", path.display());
+            }
+            EXIT_SUCCESS
+        }
+        // the scene is already read, so a file error is about the output
+        Err(SceneError::InvalidFile(err)) => {
+            eprintln!("error: cannot write {}: {err}", output.display());
+            EXIT_OUTPUT
+        }
+        Err(err) => scene_error(&args.scene, &err),
+    }
+}
+
+/// Returns the output of `args`, or the default one named after the scene:
+/// an image with the extension of the format, or a directory for the frames
+/// of an animated scene rendered to a still image format.
+fn output_path(args: &RenderArgs, animated: bool) -> PathBuf {
+    let stem = Path::new(args.scene.file_stem().unwrap_or_default());
+    match (&args.output, args.format) {
+        (Some(output), _) => output.clone(),
+        (None, Some(format)) if animated && format.encoding().is_ok() => stem.to_path_buf(),
+        (None, Some(format)) => stem.with_extension(format.extension()),
+        (None, None) => stem.with_extension(if animated { "gif" } else { "png" }),
+    }
+}
+
+/// Reports a scene error and returns the exit code of its kind.
+fn scene_error(path: &Path, err: &SceneError) -> u8 {
+    eprintln!("error: {}: {err}", path.display());
+    exit_code(err)
+}
+
+/// Returns the exit code of a scene error. Errors in included files have the
+/// code of the error they wrap.
+fn exit_code(err: &SceneError) -> u8 {
+    match err {
+        SceneError::Included { source, .. } => exit_code(source),
+        SceneError::InvalidFile(_) => EXIT_UNREADABLE,
+        SceneError::InvalidScene(_)
+        | SceneError::InvalidJson(_)
+        | SceneError::InvalidYaml(_)
+        | SceneError::InvalidYamlEntry { .. }
+        | SceneError::InvalidExpression { .. }
+        | SceneError::UnsupportedVersion(_)
+        | SceneError::Serialize(_) => EXIT_MALFORMED,
+        SceneError::Unsupported(_)
+        | SceneError::UnknownMaterial(_)
+        | SceneError::MaterialCycle(_)
+        | SceneError::IncludeCycle(_)
+        | SceneError::Validation(_) => EXIT_INVALID,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ray_tracer::scene::Canvas;
+    use scene_loader::format::SceneFormat;
+    use scene_loader::parse_scene_from_str;
+    use std::fs;
+
+    #[derive(clap::Parser)]
+    struct Cli {
+        #[command(flatten)]
+        args: RenderArgs,
+    }
+
+    fn run(args: &[&str]) -> ExitCode {
+        let cli = <Cli as clap::Parser>::try_parse_from(["render"].iter().chain(args)).unwrap();
+        render(cli.args)
+    }
+
+    const SCENE: &str = r#"
+        version = 2
+
+        [[lights]]
+        type = "point"
+        position = [-10, 10, -10]
+        intensity = [1, 1, 1]
+
+        [[objects]]
+        type = "sphere"
+        position = [0, 0, 0]
+        radius = 1.0
+    "#;
+
+    #[test]
+    fn render_ok() {
+        let dir = tempfile::tempdir().unwrap();
+        let scene = dir.path().join("scene.toml");
+        fs::write(&scene, SCENE).unwrap();
+        let scene = scene.to_str().unwrap();
+
+        let image = dir.path().join("still.ppm");
+        let code = run(&[
+            scene,
+            "-o",
+            image.to_str().unwrap(),
+            "-r",
+            "8x6",
+            "--spp",
+            "4",
+        ]);
+        assert_eq!(code, ExitCode::SUCCESS);
+        let canvas = Canvas::load(&image).unwrap();
+        assert_eq!((canvas.width, canvas.height), (8, 6));
+
+        let animated = format!("{SCENE}\n[animation]\nframes = 2\n");
+        fs::write(dir.path().join("scene.toml"), animated).unwrap();
+        let frames = dir.path().join("frames");
+        let code = run(&[
+            scene,
+            "-o",
+            frames.to_str().unwrap(),
+            "-f",
+            "png",
+            "-r",
+            "4x4",
+        ]);
+        assert_eq!(code, ExitCode::SUCCESS);
+        assert!(frames.join("frame_0002.png").exists());
+
+        // a `.png` file of an animated scene is an animated PNG
+        let image = dir.path().join("animated.png");
+        let code = run(&[scene, "-o", image.to_str().unwrap(), "-r", "4x4"]);
+        assert_eq!(code, ExitCode::SUCCESS);
+        let data = fs::read(&image).unwrap();
+        assert!(data.windows(4).any(|chunk| chunk == b"acTL"));
+    }
+
+    #[test]
+    fn output_path_ok() {
+        let output = |args: &[&str], animated: bool| {
+            let cli = <Cli as clap::Parser>::try_parse_from(
+                ["render", "scenes/scene.toml"].iter().chain(args),
+            )
+            .unwrap();
+            output_path(&cli.args, animated)
+        };
+        assert_eq!(output(&[], false), Path::new("scene.png"));
+        assert_eq!(output(&[], true), Path::new("scene.gif"));
+        assert_eq!(output(&["-f", "hdr"], false), Path::new("scene.hdr"));
+        assert_eq!(output(&["-f", "apng"], true), Path::new("scene.png"));
+        // the frames of an animated scene go into a directory
+        assert_eq!(output(&["-f", "png"], true), Path::new("scene"));
+        assert_eq!(output(&["-o", "out.ppm"], true), Path::new("out.ppm"));
+    }
+
+    #[test]
+    fn render_yields_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let scene = dir.path().join("scene.toml");
+        let output = dir.path().join("out.png");
+        let output = output.to_str().unwrap();
+
+        let code = run(&[scene.to_str().unwrap(), "-o", output]);
+        assert_eq!(code, ExitCode::from(EXIT_UNREADABLE));
+
+        fs::write(&scene, SCENE.replace("radius = 1.0", "radius = -1.0")).unwrap();
+        let code = run(&[scene.to_str().unwrap(), "-o", output]);
+        assert_eq!(code, ExitCode::from(EXIT_INVALID));
+
+        fs::write(&scene, SCENE).unwrap();
+        let code = run(&[scene.to_str().unwrap(), "-o", "out.unknown"]);
+        assert_eq!(code, ExitCode::from(EXIT_USAGE));
+
+        let missing = dir.path().join("missing").join("out.png");
+        let code = run(&[
+            scene.to_str().unwrap(),
+            "-o",
+            missing.to_str().unwrap(),
+            "-r",
+            "2x2",
+        ]);
+        assert_eq!(code, ExitCode::from(EXIT_OUTPUT));
+    }
+
+    #[test]
+    fn render_loaded_yields_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let scene = dir.path().join("scene.toml");
+        let output = dir.path().join("out.ppm");
+        fs::write(&scene, SCENE).unwrap();
+        let cli = <Cli as clap::Parser>::try_parse_from([
+            "render",
+            scene.to_str().unwrap(),
+            "-o",
+            output.to_str().unwrap(),
+            "-r",
+            "2x2",
+        ])
+        .unwrap();
+        let mut watcher = SceneWatcher::new(&scene);
+        assert_eq!(render_loaded(&cli.args, watcher.load()), EXIT_SUCCESS);
+        let image = fs::read(&output).unwrap();
+
+        // a broken scene leaves the last image as it was
+        fs::write(&scene, SCENE.replace("radius = 1.0", "radius = ")).unwrap();
+        assert!(watcher.changed());
+        assert_eq!(render_loaded(&cli.args, watcher.load()), EXIT_MALFORMED);
+        assert_eq!(fs::read(&output).unwrap(), image);
+    }
+
+    #[test]
+    fn exit_code_ok() {
+        let err = |text: &str| parse_scene_from_str(text, SceneFormat::Toml).unwrap_err();
+        assert_eq!(exit_code(&err("version = ")), EXIT_MALFORMED);
+        assert_eq!(exit_code(&err("version = 2")), EXIT_INVALID);
+
+        let missing = scene_loader::load_scene("missing.toml").unwrap_err();
+        assert_eq!(exit_code(&missing), EXIT_UNREADABLE);
+        let included = SceneError::Included {
+            chain: vec![PathBuf::from("a.toml"), PathBuf::from("b.toml")],
+            source: Box::new(missing),
+        };
+        assert_eq!(exit_code(&included), EXIT_UNREADABLE);
+    }
+
+    #[test]
+    fn output_format_ok() {
+        assert_eq!(
+            OutputFormat::from_path(Path::new("out/a.PNG"), false),
+            Some(OutputFormat::Png)
+        );
+        assert_eq!(
+            OutputFormat::from_path(Path::new("out/a.png"), true),
+            Some(OutputFormat::Apng)
+        );
+        assert_eq!(OutputFormat::from_path(Path::new("out/a"), false), None);
+        assert_eq!(OutputFormat::Apng.extension(), "png");
+        assert_eq!(OutputFormat::Gif.encoding(), Err(AnimationFormat::Gif));
+        assert_eq!(
+            OutputFormat::Png16.encoding(),
+            Ok(ImageFormat::Png(PngFormat::Rgb16))
+        );
+    }
+}
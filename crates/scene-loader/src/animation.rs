@@ -0,0 +1,440 @@
+use crate::builder::{RenderScene, build_scene};
+use crate::error::SceneError;
+use crate::validate::{Segment, ValidationIssue};
+use ray_tracer::scene::canvas::{
+    AnimationFormat, AnimationOptions, ImageFormat, save_animation, write_animation,
+};
+use ray_tracer::scene::{Canvas, RenderSettings};
+use scene_types::{InterpolationDef, KeyValueDef, SceneFile, TrackDef};
+use serde_json::Value;
+use std::fs;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// Returns the number of frames of a scene: that of its animation, or 1 for
+/// a still scene.
+pub fn frame_count(scene: &SceneFile) -> u32 {
+    scene
+        .animation
+        .as_ref()
+        .map_or(1, |animation| animation.frames)
+}
+
+/// Returns the still scene shown at `frame`, counted from 1, with every
+/// track of its animation applied.
+///
+/// Tracks are applied in order, so a later track wins when two animate the
+/// same value. The returned scene has no animation.
+pub fn scene_at_frame(scene: &SceneFile, frame: u32) -> Result<SceneFile, SceneError> {
+    let Some(animation) = &scene.animation else {
+        return Ok(scene.clone());
+    };
+    let mut value =
+        serde_json::to_value(scene).map_err(|err| SceneError::Serialize(err.to_string()))?;
+    if let Some(scene) = value.as_object_mut() {
+        scene.remove("animation");
+    }
+    for (i, track) in animation.tracks.iter().enumerate() {
+        let Some(key) = track_value(track, frame) else {
+            continue;
+        };
+        set_target(&mut value, &track.target, &key)
+            .map_err(|message| track_error(i, frame, message))?;
+    }
+    serde_json::from_value(value).map_err(|err| {
+        SceneError::Validation(vec![issue(
+            "animation".to_string(),
+            format!("frame {frame}: {err}"),
+        )])
+    })
+}
+
+/// Builds the world and camera of one frame of a scene, counted from 1.
+pub fn build_frame(scene: &SceneFile, frame: u32) -> Result<RenderScene, SceneError> {
+    build_scene(&scene_at_frame(scene, frame)?)
+}
+
+/// Renders one frame of a scene, counted from 1, as described by
+/// `settings`.
+pub fn render_frame(
+    scene: &SceneFile,
+    frame: u32,
+    settings: &RenderSettings,
+) -> Result<Canvas, SceneError> {
+    let render = build_frame(scene, frame)?;
+    Ok(render.camera.render_with(&render.world, settings))
+}
+
+/// Renders every frame of a scene into `dir` as `frame_0001.ppm`,
+/// `frame_0002.ppm`, and so on, with the extension of `format`, and returns
+/// the paths written. `dir` is created when missing.
+pub fn render_frames(
+    scene: &SceneFile,
+    dir: &Path,
+    settings: &RenderSettings,
+    format: ImageFormat,
+) -> Result<Vec<PathBuf>, SceneError> {
+    fs::create_dir_all(dir)?;
+    (1..=frame_count(scene))
+        .map(|frame| {
+            let path = dir.join(format!("frame_{frame:04}.{}", format.extension()));
+            render_frame(scene, frame, settings)?.save_as(&path, format)?;
+            Ok(path)
+        })
+        .collect()
+}
+
+/// Renders every frame of a scene into a single animated GIF or APNG at
+/// `path`, chosen by its extension, played back at the animation's `fps`.
+/// GIF colors are dithered when `dither` is set.
+pub fn render_animation(
+    scene: &SceneFile,
+    path: &Path,
+    settings: &RenderSettings,
+    dither: bool,
+) -> Result<(), SceneError> {
+    let (frames, options) = render_all(scene, settings, dither)?;
+    save_animation(&frames, path, options)?;
+    Ok(())
+}
+
+/// Renders every frame of a scene like `render_animation`, into an
+/// animated image of the given `format` whatever the extension of `path`.
+pub fn render_animation_as(
+    scene: &SceneFile,
+    path: &Path,
+    settings: &RenderSettings,
+    format: AnimationFormat,
+    dither: bool,
+) -> Result<(), SceneError> {
+    let (frames, options) = render_all(scene, settings, dither)?;
+    let mut writer = BufWriter::new(fs::File::create(path)?);
+    write_animation(&frames, &mut writer, format, options)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Renders every frame of a scene, with the options to play them back at
+/// the animation's `fps`.
+fn render_all(
+    scene: &SceneFile,
+    settings: &RenderSettings,
+    dither: bool,
+) -> Result<(Vec<Canvas>, AnimationOptions), SceneError> {
+    let frames = (1..=frame_count(scene))
+        .map(|frame| render_frame(scene, frame, settings))
+        .collect::<Result<Vec<_>, _>>()?;
+    let fps = scene
+        .animation
+        .as_ref()
+        .map_or(AnimationOptions::default().fps, |animation| {
+            animation.fps as f64
+        });
+    Ok((frames, AnimationOptions { fps, dither }))
+}
+
+/// Interpolates a track at `frame`. Before the first keyframe and after the
+/// last one, the nearest keyframe holds. Returns `None` for a track without
+/// keyframes.
+fn track_value(track: &TrackDef, frame: u32) -> Option<KeyValueDef> {
+    let keyframes = &track.keyframes;
+    let next = keyframes.iter().position(|key| key.frame > frame);
+    let (from, to) = match next {
+        Some(0) => return keyframes.first().map(|key| key.value.clone()),
+        None => return keyframes.last().map(|key| key.value.clone()),
+        Some(i) => (&keyframes[i - 1], &keyframes[i]),
+    };
+    let mut t = (frame - from.frame) as f32 / (to.frame - from.frame) as f32;
+    if track.interpolation == InterpolationDef::Smooth {
+        t = t * t * (3.0 - 2.0 * t);
+    }
+    let lerp = |a: f32, b: f32| a + (b - a) * t;
+    Some(match (&from.value, &to.value) {
+        (KeyValueDef::Scalar(a), KeyValueDef::Scalar(b)) => KeyValueDef::Scalar(lerp(*a, *b)),
+        (KeyValueDef::Vector(a), KeyValueDef::Vector(b)) if a.len() == b.len() => {
+            KeyValueDef::Vector(a.iter().zip(b).map(|(a, b)| lerp(*a, *b)).collect())
+        }
+        // mismatched keyframes are rejected by validation; hold the first
+        _ => from.value.clone(),
+    })
+}
+
+/// Parses a track target such as `objects[0].transform[1].rotate_y`.
+pub(crate) fn parse_target(target: &str) -> Option<Vec<Segment<'_>>> {
+    let mut path = Vec::new();
+    for part in target.split('.') {
+        let mut pieces = part.split('[');
+        let key = pieces.next().filter(|key| !key.is_empty())?;
+        path.push(Segment::Key(key));
+        for index in pieces {
+            let index = index.strip_suffix(']')?.parse().ok()?;
+            path.push(Segment::Index(index));
+        }
+    }
+    Some(path)
+}
+
+/// Checks that `target` names a value of a serialized scene that `key` can
+/// replace, including omitted fields that the scene format accepts.
+pub(crate) fn check_target(scene: &Value, target: &str, key: &KeyValueDef) -> Result<(), String> {
+    let mut scene = scene.clone();
+    set_target(&mut scene, target, key)?;
+    serde_json::from_value::<SceneFile>(scene)
+        .map(|_| ())
+        .map_err(|err| format!("`{target}` cannot be animated: {err}"))
+}
+
+/// Replaces the value at `target` in a serialized scene. The last key may
+/// name an omitted field, such as a coefficient of an inline material.
+/// Integer values, such as 8-bit colors, are rounded.
+fn set_target(scene: &mut Value, target: &str, key: &KeyValueDef) -> Result<(), String> {
+    let path = parse_target(target).ok_or_else(|| format!("`{target}` is not a valid path"))?;
+    let (last, parents) = path.split_last().expect("a parsed path is never empty");
+    let mut parent = scene;
+    for segment in parents {
+        let next = match segment {
+            Segment::Key(key) => parent.get_mut(*key),
+            Segment::Index(i) => parent.get_mut(*i),
+        };
+        parent = next.ok_or_else(|| format!("`{target}` does not name a value of the scene"))?;
+    }
+    let slot = match (last, parent) {
+        (Segment::Key(name), Value::Object(map)) => map.entry(*name).or_insert(Value::Null),
+        (Segment::Index(i), Value::Array(items)) if *i < items.len() => &mut items[*i],
+        _ => return Err(format!("`{target}` does not name a value of the scene")),
+    };
+    *slot = match (key, &*slot) {
+        (KeyValueDef::Scalar(v), Value::Number(_) | Value::Null) => number_like(*v, slot),
+        (KeyValueDef::Vector(vs), Value::Array(items))
+            if vs.len() == items.len() && items.iter().all(Value::is_number) =>
+        {
+            vs.iter()
+                .zip(items)
+                .map(|(v, item)| number_like(*v, item))
+                .collect()
+        }
+        (KeyValueDef::Vector(vs), Value::Array(items)) if items.iter().all(Value::is_number) => {
+            return Err(format!(
+                "`{target}` has {} numbers, but the keyframes have {}",
+                items.len(),
+                vs.len()
+            ));
+        }
+        (KeyValueDef::Scalar(_), _) => return Err(format!("`{target}` is not a number")),
+        (KeyValueDef::Vector(_), _) => {
+            return Err(format!("`{target}` is not an array of numbers"));
+        }
+    };
+    Ok(())
+}
+
+/// Converts `value` to a JSON number, rounded when `like` is an integer.
+fn number_like(value: f32, like: &Value) -> Value {
+    if like.is_i64() || like.is_u64() {
+        (value.round() as i64).into()
+    } else {
+        (value as f64).into()
+    }
+}
+
+fn track_error(track: usize, frame: u32, message: String) -> SceneError {
+    SceneError::Validation(vec![issue(
+        format!("animation.tracks[{track}].target"),
+        format!("frame {frame}: {message}"),
+    )])
+}
+
+fn issue(path: String, message: String) -> ValidationIssue {
+    ValidationIssue {
+        path,
+        message,
+        file: None,
+        location: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::toml::parse_toml_scene_from_str;
+    use crate::writer::scene_to_toml_string;
+    use scene_types::{MaterialDef, TransformDef};
+    use std::f32::consts::PI;
+
+    const SCENE: &str = r#"
+        version = 2
+
+        [camera]
+        position = [0, 0, -5]
+
+        [[lights]]
+        type = "point"
+        position = [-10, 10, -10]
+        intensity = [1, 1, 1]
+
+        [[objects]]
+        type = "sphere"
+        position = [0, 0, 0]
+        radius = 1.0
+        color = [0, 0, 0]
+        transform = [{ rotate_y = 0 }]
+
+        [animation]
+        frames = 5
+
+        [[animation.tracks]]
+        target = "objects[0].transform[0].rotate_y"
+        keyframes = [
+            { frame = 1, value = 0 },
+            { frame = 5, value = "deg(360)" },
+        ]
+
+        [[animation.tracks]]
+        target = "camera.position"
+        interpolation = "smooth"
+        keyframes = [
+            { frame = 2, value = [0, 0, -5] },
+            { frame = 4, value = [0, 2, -9] },
+        ]
+
+        [[animation.tracks]]
+        target = "objects[0].color"
+        keyframes = [
+            { frame = 1, value = [0, 0, 0] },
+            { frame = 4, value = [255, 128, 1] },
+        ]
+
+        [[animation.tracks]]
+        target = "objects[0].material.shininess"
+        keyframes = [{ frame = 1, value = 50 }]
+    "#;
+
+    #[test]
+    fn scene_at_frame_ok() {
+        let scene = parse_toml_scene_from_str(SCENE).unwrap();
+        assert_eq!(frame_count(&scene), 5);
+        let written = scene_to_toml_string(&scene).unwrap();
+        assert_eq!(parse_toml_scene_from_str(&written).unwrap(), scene);
+
+        let first = scene_at_frame(&scene, 1).unwrap();
+        assert_eq!(first.animation, None);
+        assert_eq!(first.camera.position, [0.0, 0.0, -5.0]);
+        assert_eq!(first.objects[0].transform[0], TransformDef::RotateY(0.0));
+        let MaterialDef::Inline(material) = &first.objects[0].material else {
+            panic!("expected an inline material");
+        };
+        assert_eq!(material.shininess, Some(50.0));
+
+        let middle = scene_at_frame(&scene, 3).unwrap();
+        assert_eq!(middle.objects[0].transform[0], TransformDef::RotateY(PI));
+        assert_eq!(middle.camera.position, [0.0, 1.0, -7.0]);
+        assert_eq!(middle.objects[0].color, [170, 85, 1]);
+
+        let last = scene_at_frame(&scene, 5).unwrap();
+        assert_eq!(
+            last.objects[0].transform[0],
+            TransformDef::RotateY(2.0 * PI)
+        );
+        assert_eq!(last.camera.position, [0.0, 2.0, -9.0]);
+        assert_eq!(last.objects[0].color, [255, 128, 1]);
+
+        let track = &scene.animation.as_ref().unwrap().tracks[1];
+        assert_eq!(
+            track_value(track, 3),
+            Some(KeyValueDef::Vector(vec![0.0, 1.0, -7.0]))
+        );
+        let linear = TrackDef {
+            interpolation: InterpolationDef::Linear,
+            keyframes: vec![track.keyframes[0].clone(), {
+                let mut key = track.keyframes[1].clone();
+                key.frame = 6;
+                key
+            }],
+            ..track.clone()
+        };
+        // a quarter of the way, smooth interpolation would be at 0.15625
+        assert_eq!(
+            track_value(&linear, 3),
+            Some(KeyValueDef::Vector(vec![0.0, 0.5, -6.0]))
+        );
+    }
+
+    #[test]
+    fn scene_at_frame_yields_error() {
+        let mut scene = parse_toml_scene_from_str(SCENE).unwrap();
+        scene.animation.as_mut().unwrap().tracks[0].target = "objects[1].radius".to_string();
+        let err = scene_at_frame(&scene, 2).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid scene: animation.tracks[0].target: frame 2: \
+             `objects[1].radius` does not name a value of the scene"
+        );
+    }
+
+    #[test]
+    fn parse_target_ok() {
+        assert_eq!(
+            parse_target("objects[0].transform[12].rotate_y"),
+            Some(vec![
+                Segment::Key("objects"),
+                Segment::Index(0),
+                Segment::Key("transform"),
+                Segment::Index(12),
+                Segment::Key("rotate_y"),
+            ])
+        );
+        assert_eq!(parse_target("objects[0"), None);
+        assert_eq!(parse_target("camera..fov"), None);
+        assert_eq!(parse_target("[0]"), None);
+    }
+
+    fn settings() -> RenderSettings {
+        RenderSettings {
+            width: 4,
+            height: 3,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn render_frames_ok() {
+        let dir = tempfile::tempdir().expect("create temp dir");
+        let scene = parse_toml_scene_from_str(SCENE).unwrap();
+        let format = ImageFormat::from_extension("ppm").unwrap();
+        let paths = render_frames(&scene, &dir.path().join("frames"), &settings(), format).unwrap();
+
+        let names: Vec<_> = paths
+            .iter()
+            .map(|path| path.file_name().unwrap().to_string_lossy().into_owned())
+            .collect();
+        assert_eq!(
+            names,
+            [
+                "frame_0001.ppm",
+                "frame_0002.ppm",
+                "frame_0003.ppm",
+                "frame_0004.ppm",
+                "frame_0005.ppm"
+            ]
+        );
+        let frame = Canvas::load(&paths[4]).unwrap();
+        assert_eq!((frame.width, frame.height), (4, 3));
+    }
+
+    #[test]
+    fn render_animation_ok() {
+        let dir = tempfile::tempdir().expect("create temp dir");
+        let scene = parse_toml_scene_from_str(SCENE).unwrap();
+        let path = dir.path().join("turntable.gif");
+        render_animation(&scene, &path, &settings(), true).unwrap();
+        assert!(fs::read(&path).unwrap().starts_with(b"GIF89a"));
+
+        let err = render_animation(&scene, &dir.path().join("turntable.ppm"), &settings(), true);
+        assert!(matches!(err, Err(SceneError::InvalidFile(_))));
+
+        // the format given wins over the extension
+        let path = dir.path().join("turntable.bin");
+        render_animation_as(&scene, &path, &settings(), AnimationFormat::Apng, true).unwrap();
+        assert!(fs::read(&path).unwrap().starts_with(b"\x89PNG"));
+    }
+}
@@ -0,0 +1,392 @@
+use crate::error::SceneError;
+use crate::material::{resolve_inline_material, resolve_material};
+use crate::validate::validate_scene;
+use ray_tracer::lighting::Light;
+use ray_tracer::math::{Matrix, Tuple};
+use ray_tracer::scene::{Camera, World};
+use ray_tracer::shape::{Color, Material, Plane, Shape, Sphere};
+use scene_types::{
+    AmbientDef, CameraDef, LightDef, LightKindDef, MaterialDef, NamedMaterialDef, ObjectDef,
+    SceneFile, ShapeDef, TransformDef,
+};
+use std::collections::BTreeMap;
+
+/// A scene converted into ray-tracer types, ready to be rendered.
+#[derive(Debug)]
+pub struct RenderScene {
+    pub world: World,
+    pub camera: Camera,
+}
+
+/// Converts a parsed scene into a `World` and a `Camera`.
+///
+/// The global ambient light is folded into every material: its intensity
+/// scales the material's ambient coefficient. The ray tracer has no separate
+/// ambient color, so only white ambient light is supported.
+pub fn build_scene(scene: &SceneFile) -> Result<RenderScene, SceneError> {
+    validate_scene(scene)?;
+    Ok(RenderScene {
+        world: build_world(scene)?,
+        camera: build_camera(&scene.camera),
+    })
+}
+
+/// Converts the lights and objects of a parsed scene into a `World`.
+pub fn build_world(scene: &SceneFile) -> Result<World, SceneError> {
+    if scene.lights.is_empty() {
+        return Err(SceneError::Unsupported(
+            "a scene needs at least one light".to_string(),
+        ));
+    }
+    let ambient = build_ambient(&scene.ambient)?;
+
+    let lights: Vec<Light> = scene.lights.iter().map(build_light).collect();
+    let objects: Vec<Shape> = scene
+        .objects
+        .iter()
+        .map(|object| build_object(object, &scene.materials, ambient))
+        .collect::<Result<_, _>>()?;
+
+    Ok(World { objects, lights })
+}
+
+/// Converts a camera definition into a `Camera`.
+pub fn build_camera(camera: &CameraDef) -> Camera {
+    Camera::new(
+        point_from(camera.position),
+        point_from(camera.target),
+        camera.fov as f64,
+    )
+}
+
+/// Converts a light definition into a point light.
+pub fn build_light(light: &LightDef) -> Light {
+    let [r, g, b] = light.intensity;
+    match light.kind {
+        LightKindDef::Point => Light::point_light(
+            point_from(light.position),
+            Color::new(r as f64, g as f64, b as f64),
+        ),
+    }
+}
+
+/// Converts an object definition into a shape. Named materials are looked
+/// up in `materials`, and `ambient` scales the material's ambient
+/// coefficient.
+///
+/// The shape is sized first, then transformed by the object's `transform`
+/// list and finally moved to its `position`.
+pub fn build_object(
+    object: &ObjectDef,
+    materials: &BTreeMap<String, NamedMaterialDef>,
+    ambient: f64,
+) -> Result<Shape, SceneError> {
+    let mut material = build_material(&object.material, materials)?;
+    material.color = color_from(object.color);
+    material.ambient *= ambient;
+
+    let placement = Matrix::translation(
+        object.position[0] as f64,
+        object.position[1] as f64,
+        object.position[2] as f64,
+    ) * build_transform(&object.transform);
+    match object.shape {
+        ShapeDef::Sphere { radius } => {
+            let r = radius as f64;
+            let mut sphere = Sphere::new();
+            sphere.set_transformation(placement * Matrix::scaling(r, r, r));
+            sphere.set_material(material);
+            Ok(Shape::Sphere(sphere))
+        }
+        ShapeDef::Plane => {
+            let mut plane = Plane::new();
+            plane.set_transformation(placement);
+            plane.set_material(material);
+            Ok(Shape::Plane(plane))
+        }
+    }
+}
+
+/// Composes a transform list into a single matrix. The first step is the
+/// first one applied to the object, so it ends up rightmost in the product.
+pub fn build_transform(steps: &[TransformDef]) -> Matrix<4> {
+    steps.iter().fold(Matrix::identity(), |acc, step| {
+        build_transform_step(step) * acc
+    })
+}
+
+/// Converts a single transform step into a matrix.
+pub fn build_transform_step(step: &TransformDef) -> Matrix<4> {
+    match step {
+        TransformDef::Translate([x, y, z]) => Matrix::translation(*x as f64, *y as f64, *z as f64),
+        TransformDef::Scale([x, y, z]) => Matrix::scaling(*x as f64, *y as f64, *z as f64),
+        TransformDef::RotateX(radians) => Matrix::rotation_x(*radians as f64),
+        TransformDef::RotateY(radians) => Matrix::rotation_y(*radians as f64),
+        TransformDef::RotateZ(radians) => Matrix::rotation_z(*radians as f64),
+        TransformDef::Shear(s) => Matrix::shearing(
+            s.xy as f64,
+            s.xz as f64,
+            s.yx as f64,
+            s.yz as f64,
+            s.zx as f64,
+            s.zy as f64,
+        ),
+        TransformDef::Matrix(rows) => Matrix::from(rows.map(|row| row.map(|v| v as f64))),
+    }
+}
+
+/// Converts a material definition. Named materials and the entries that
+/// inline materials extend are resolved from `materials`; fields nothing
+/// sets come from `MaterialCustomDef::default()`.
+pub fn build_material(
+    material: &MaterialDef,
+    materials: &BTreeMap<String, NamedMaterialDef>,
+) -> Result<Material, SceneError> {
+    match material {
+        MaterialDef::Inline(def) => resolve_inline_material(def, materials),
+        MaterialDef::Named(name) => resolve_material(name, materials),
+    }
+}
+
+/// Returns the factor applied to every material's ambient coefficient.
+fn build_ambient(ambient: &AmbientDef) -> Result<f64, SceneError> {
+    if ambient.color != [255, 255, 255] {
+        return Err(SceneError::Unsupported(format!(
+            "ambient color {:?} is not white",
+            ambient.color
+        )));
+    }
+    Ok(ambient.intensity as f64)
+}
+
+/// Converts an 8-bit color into a linear `Color` in `0.0..=1.0`.
+pub fn color_from(rgb: [u8; 3]) -> Color {
+    Color::new(
+        rgb[0] as f64 / 255.0,
+        rgb[1] as f64 / 255.0,
+        rgb[2] as f64 / 255.0,
+    )
+}
+
+fn point_from(p: [f32; 3]) -> Tuple {
+    Tuple::point(p[0] as f64, p[1] as f64, p[2] as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::material_from;
+    use crate::toml::parse_toml_scene_from_str;
+    use scene_types::MaterialCustomDef;
+
+    const SCENE: &str = r#"
+        version = 2
+
+        [camera]
+        position = [0, 0, -5]
+        target = [0, 0, 0]
+        fov = 60.0
+
+        [ambient]
+        intensity = 0.5
+        color = [255, 255, 255]
+
+        [[lights]]
+        type = "point"
+        position = [-10, 10, -10]
+        intensity = [0.5, 0.5, 0.5]
+
+        [[lights]]
+        type = "point"
+        position = [10, 10, -10]
+        intensity = [1, 0, 0]
+
+        [[objects]]
+        type = "sphere"
+        position = [1, 2, 3]
+        radius = 2.0
+        color = [255, 0, 0]
+
+        [[objects]]
+        type = "sphere"
+        position = [0, 0, 0]
+        radius = 1.0
+        material = {
+                ambient-coefficient = 0.2,
+                diffuse-coefficient = 0.7,
+                specular-coefficient = 0.3,
+                shininess = 50.0
+            }
+        color = [0, 255, 0]
+        transform = [
+            { scale = [1, 2, 1] },
+            { rotate_z = 1.5707963 },
+            { translate = [0, 0, 1] },
+        ]
+    "#;
+
+    #[test]
+    fn build_scene_ok() {
+        let scene = parse_toml_scene_from_str(SCENE).unwrap();
+        let res = build_scene(&scene).unwrap();
+
+        assert_eq!(
+            res.camera,
+            Camera::new(
+                Tuple::point(0.0, 0.0, -5.0),
+                Tuple::point(0.0, 0.0, 0.0),
+                60.0
+            )
+        );
+
+        assert_eq!(res.world.lights.len(), 2);
+        assert_eq!(
+            res.world.lights[0].position,
+            Tuple::point(-10.0, 10.0, -10.0)
+        );
+        assert_eq!(res.world.lights[0].intensity, Color::new(0.5, 0.5, 0.5));
+        assert_eq!(res.world.lights[1].intensity, Color::new(1.0, 0.0, 0.0));
+
+        assert_eq!(res.world.objects.len(), 2);
+        let Shape::Sphere(s1) = &res.world.objects[0] else {
+            panic!("expected a sphere");
+        };
+        assert_eq!(
+            s1.transform,
+            Matrix::translation(1.0, 2.0, 3.0) * Matrix::scaling(2.0, 2.0, 2.0)
+        );
+        let mut expected = material_from(&MaterialCustomDef::default());
+        expected.color = Color::new(1.0, 0.0, 0.0);
+        expected.ambient *= 0.5;
+        assert_eq!(s1.material, expected);
+
+        let Shape::Sphere(s2) = &res.world.objects[1] else {
+            panic!("expected a sphere");
+        };
+        assert_eq!(
+            s2.transform,
+            Matrix::translation(0.0, 0.0, 1.0)
+                * Matrix::rotation_z(std::f64::consts::FRAC_PI_2)
+                * Matrix::scaling(1.0, 2.0, 1.0)
+        );
+        assert_eq!(s2.material.color, Color::new(0.0, 1.0, 0.0));
+        assert!((s2.material.ambient - 0.1).abs() < 1e-6);
+        assert!((s2.material.diffuse - 0.7).abs() < 1e-6);
+        assert!((s2.material.specular - 0.3).abs() < 1e-6);
+        assert_eq!(s2.material.shininess, 50.0);
+    }
+
+    #[test]
+    fn build_plane_ok() {
+        let source = format!(
+            "{SCENE}
+        [[objects]]
+        type = \"plane\"
+        position = [0, -1, 0]
+        transform = [{{ rotate_x = 1.5707963 }}]
+        "
+        );
+        let scene = parse_toml_scene_from_str(&source).unwrap();
+        let res = build_scene(&scene).unwrap();
+
+        let Shape::Plane(plane) = &res.world.objects[2] else {
+            panic!("expected a plane");
+        };
+        assert_eq!(
+            plane.transform,
+            Matrix::translation(0.0, -1.0, 0.0) * Matrix::rotation_x(std::f64::consts::FRAC_PI_2)
+        );
+        assert_eq!(plane.material.color, Color::new(1.0, 1.0, 1.0));
+        assert!((plane.material.ambient - 0.05).abs() < 1e-6);
+    }
+
+    #[test]
+    fn build_transform_ok() {
+        assert_eq!(build_transform(&[]), Matrix::identity());
+
+        let steps: Vec<TransformDef> = vec![
+            TransformDef::RotateX(std::f32::consts::FRAC_PI_2),
+            TransformDef::Scale([5.0, 5.0, 5.0]),
+            TransformDef::Translate([10.0, 5.0, 7.0]),
+        ];
+        let p = build_transform(&steps) * Tuple::point(1.0, 0.0, 1.0);
+        assert_eq!(p, Tuple::point(15.0, 0.0, 7.0));
+
+        let shear = TransformDef::Shear(scene_types::ShearDef {
+            xy: 1.0,
+            xz: 0.0,
+            yx: 0.0,
+            yz: 0.0,
+            zx: 0.0,
+            zy: 0.0,
+        });
+        assert_eq!(
+            build_transform_step(&shear) * Tuple::point(2.0, 3.0, 4.0),
+            Tuple::point(5.0, 3.0, 4.0)
+        );
+
+        let raw = TransformDef::Matrix([
+            [1.0, 0.0, 0.0, 1.0],
+            [0.0, 2.0, 0.0, 2.0],
+            [0.0, 0.0, 3.0, 3.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ]);
+        assert_eq!(
+            build_transform_step(&raw),
+            Matrix::translation(1.0, 2.0, 3.0) * Matrix::scaling(1.0, 2.0, 3.0)
+        );
+    }
+
+    #[test]
+    fn build_named_material_ok() {
+        let source = SCENE.replacen(
+            "[[lights]]",
+            r#"[materials.matte]
+            specular-coefficient = 0.0
+
+            [materials.dark-matte]
+            extend = "matte"
+            diffuse-coefficient = 0.25
+
+            [[lights]]"#,
+            1,
+        );
+        let source = source.replace(
+            "radius = 2.0",
+            "radius = 2.0\n        material = \"dark-matte\"",
+        );
+        let scene = parse_toml_scene_from_str(&source).unwrap();
+        let res = build_scene(&scene).unwrap();
+
+        let Shape::Sphere(s1) = &res.world.objects[0] else {
+            panic!("expected a sphere");
+        };
+        let mut expected = material_from(&MaterialCustomDef::default());
+        expected.color = Color::new(1.0, 0.0, 0.0);
+        expected.ambient *= 0.5;
+        expected.specular = 0.0;
+        expected.diffuse = 0.25;
+        assert_eq!(s1.material, expected);
+    }
+
+    #[test]
+    fn build_scene_yields_error() {
+        let mut scene = parse_toml_scene_from_str(SCENE).unwrap();
+        scene.ambient.color = [255, 200, 200];
+        let err = build_scene(&scene);
+        assert!(matches!(err, Err(SceneError::Unsupported(_))));
+
+        let mut scene = parse_toml_scene_from_str(SCENE).unwrap();
+        scene.lights.clear();
+        let err = build_scene(&scene);
+        assert!(matches!(err, Err(SceneError::Unsupported(_))));
+
+        let scene = parse_toml_scene_from_str(SCENE).unwrap();
+        let object = ObjectDef {
+            material: MaterialDef::Named("missing".to_string()),
+            ..scene.objects[0].clone()
+        };
+        let err = build_object(&object, &scene.materials, 1.0);
+        assert!(matches!(err, Err(SceneError::UnknownMaterial(_))));
+    }
+}
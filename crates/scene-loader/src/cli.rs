@@ -0,0 +1,31 @@
+//! Helpers shared by the command-line tools that render scenes.
+
+/// Parses an image size written as `WIDTHxHEIGHT`, such as `640x480`.
+pub fn parse_resolution(text: &str) -> Result<(usize, usize), String> {
+    let invalid = || format!("expected WIDTHxHEIGHT, such as 640x480, got `{text}`");
+    let (width, height) = text.split_once(['x', 'X']).ok_or_else(invalid)?;
+    let width: usize = width.trim().parse().map_err(|_| invalid())?;
+    let height: usize = height.trim().parse().map_err(|_| invalid())?;
+    if width == 0 || height == 0 {
+        return Err(format!("the image must not be empty, got `{text}`"));
+    }
+    Ok((width, height))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_resolution_ok() {
+        assert_eq!(parse_resolution("640x480"), Ok((640, 480)));
+        assert_eq!(parse_resolution("32X16"), Ok((32, 16)));
+    }
+
+    #[test]
+    fn parse_resolution_yields_error() {
+        assert!(parse_resolution("640").is_err());
+        assert!(parse_resolution("640x").is_err());
+        assert!(parse_resolution("0x480").is_err());
+    }
+}
@@ -1,3 +1,6 @@
+use crate::include::{describe_include_chain, describe_include_cycle};
+use crate::validate::{ValidationIssue, describe_issues};
+use std::path::PathBuf;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -7,4 +10,46 @@ pub enum SceneError {
 
     #[error(transparent)]
     InvalidFile(#[from] std::io::Error),
+
+    #[error(transparent)]
+    InvalidJson(#[from] serde_json::Error),
+
+    #[error(transparent)]
+    InvalidYaml(#[from] serde_yaml::Error),
+
+    #[error("YAML item {index}: {message}")]
+    InvalidYamlEntry { index: usize, message: String },
+
+    #[error("unsupported scene: {0}")]
+    Unsupported(String),
+
+    #[error(
+        "unsupported scene version {0}, expected 1 to {current}",
+        current = scene_types::CURRENT_VERSION
+    )]
+    UnsupportedVersion(u32),
+
+    #[error("invalid expression at `{path}`: {message}")]
+    InvalidExpression { path: String, message: String },
+
+    #[error("cannot serialize scene: {0}")]
+    Serialize(String),
+
+    #[error("unknown material `{0}`")]
+    UnknownMaterial(String),
+
+    #[error("material inheritance cycle: {}", .0.join(" -> "))]
+    MaterialCycle(Vec<String>),
+
+    #[error("include cycle: {}", describe_include_cycle(.0))]
+    IncludeCycle(Vec<PathBuf>),
+
+    #[error("{}: {source}", describe_include_chain(.chain))]
+    Included {
+        chain: Vec<PathBuf>,
+        source: Box<SceneError>,
+    },
+
+    #[error("invalid scene: {}", describe_issues(.0))]
+    Validation(Vec<ValidationIssue>),
 }
@@ -0,0 +1,607 @@
+use crate::error::SceneError;
+use scene_types::SceneFile;
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::f64::consts::{PI, TAU};
+use std::sync::OnceLock;
+
+/// How deeply parentheses, signs and variables referring to variables may
+/// nest, so that a malformed scene is an error rather than a stack overflow.
+const MAX_DEPTH: usize = 128;
+
+/// Evaluates an arithmetic expression such as `$radius * 2` or `deg(45)`.
+///
+/// Expressions combine numbers, `$name` variables and the constants `pi`
+/// and `tau` with `+`, `-`, `*`, `/`, `%` and parentheses. The functions
+/// are `deg` (degrees to radians), `sqrt`, `sin`, `cos`, `tan`, `abs`,
+/// `min` and `max`.
+pub fn evaluate_expression(text: &str, vars: &BTreeMap<String, f64>) -> Result<f64, String> {
+    evaluate_with(text, &mut |name| {
+        vars.get(name)
+            .copied()
+            .ok_or_else(|| format!("unknown variable `${name}`"))
+    })
+}
+
+fn evaluate_with(
+    text: &str,
+    lookup: &mut dyn FnMut(&str) -> Result<f64, String>,
+) -> Result<f64, String> {
+    let mut parser = Parser {
+        text,
+        pos: 0,
+        depth: 0,
+        lookup,
+    };
+    let value = parser.expr()?;
+    parser.skip_spaces();
+    if let Some(c) = parser.peek() {
+        return Err(parser.unexpected(c));
+    }
+    if !value.is_finite() {
+        return Err(format!("`{text}` is not a finite number"));
+    }
+    Ok(value)
+}
+
+struct Parser<'a, 'l> {
+    text: &'a str,
+    pos: usize,
+    depth: usize,
+    lookup: &'l mut dyn FnMut(&str) -> Result<f64, String>,
+}
+
+impl<'a> Parser<'a, '_> {
+    fn expr(&mut self) -> Result<f64, String> {
+        let mut value = self.term()?;
+        loop {
+            if self.eat('+') {
+                value += self.term()?;
+            } else if self.eat('-') {
+                value -= self.term()?;
+            } else {
+                return Ok(value);
+            }
+        }
+    }
+
+    fn term(&mut self) -> Result<f64, String> {
+        let mut value = self.unary()?;
+        loop {
+            if self.eat('*') {
+                value *= self.unary()?;
+            } else if self.eat('/') {
+                value /= self.unary()?;
+            } else if self.eat('%') {
+                value %= self.unary()?;
+            } else {
+                return Ok(value);
+            }
+        }
+    }
+
+    /// Every nested expression passes through here, so this is where the
+    /// nesting is limited.
+    fn unary(&mut self) -> Result<f64, String> {
+        if self.depth >= MAX_DEPTH {
+            return Err(format!(
+                "expression is nested more than {MAX_DEPTH} levels deep"
+            ));
+        }
+        self.depth += 1;
+        let value = if self.eat('-') {
+            self.unary().map(|value| -value)
+        } else if self.eat('+') {
+            self.unary()
+        } else {
+            self.atom()
+        };
+        self.depth -= 1;
+        value
+    }
+
+    fn atom(&mut self) -> Result<f64, String> {
+        self.skip_spaces();
+        match self.peek() {
+            Some('(') => {
+                self.pos += 1;
+                let value = self.expr()?;
+                self.expect(')')?;
+                Ok(value)
+            }
+            Some('$') => {
+                self.pos += 1;
+                let name = self.ident();
+                if name.is_empty() {
+                    return Err("expected a variable name after `$`".to_string());
+                }
+                (self.lookup)(name)
+            }
+            Some(c) if c.is_ascii_digit() || c == '.' => self.number(),
+            Some(c) if c.is_ascii_alphabetic() => {
+                let name = self.ident();
+                if self.eat('(') {
+                    self.call(name)
+                } else {
+                    match name {
+                        "pi" => Ok(PI),
+                        "tau" => Ok(TAU),
+                        _ => Err(format!("unknown constant `{name}`")),
+                    }
+                }
+            }
+            Some(c) => Err(self.unexpected(c)),
+            None => Err("unexpected end of expression".to_string()),
+        }
+    }
+
+    fn call(&mut self, name: &str) -> Result<f64, String> {
+        let mut args = vec![self.expr()?];
+        while self.eat(',') {
+            args.push(self.expr()?);
+        }
+        self.expect(')')?;
+        let arity = match name {
+            "min" | "max" => 2,
+            _ => 1,
+        };
+        if args.len() != arity {
+            return Err(format!(
+                "`{name}` takes {arity} argument(s), got {}",
+                args.len()
+            ));
+        }
+        match name {
+            "deg" => Ok(args[0].to_radians()),
+            "sqrt" => Ok(args[0].sqrt()),
+            "sin" => Ok(args[0].sin()),
+            "cos" => Ok(args[0].cos()),
+            "tan" => Ok(args[0].tan()),
+            "abs" => Ok(args[0].abs()),
+            "min" => Ok(args[0].min(args[1])),
+            "max" => Ok(args[0].max(args[1])),
+            _ => Err(format!("unknown function `{name}`")),
+        }
+    }
+
+    fn number(&mut self) -> Result<f64, String> {
+        let start = self.pos;
+        while let Some(c) = self.peek() {
+            let exponent_sign =
+                (c == '+' || c == '-') && self.text[..self.pos].ends_with(['e', 'E']);
+            if c.is_ascii_digit() || c == '.' || c == 'e' || c == 'E' || exponent_sign {
+                self.pos += 1;
+            } else {
+                break;
+            }
+        }
+        let literal = &self.text[start..self.pos];
+        literal
+            .parse()
+            .map_err(|_| format!("invalid number `{literal}`"))
+    }
+
+    fn ident(&mut self) -> &'a str {
+        let text = self.text;
+        let start = self.pos;
+        while self
+            .peek()
+            .is_some_and(|c| c.is_ascii_alphanumeric() || c == '_')
+        {
+            self.pos += 1;
+        }
+        &text[start..self.pos]
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        self.skip_spaces();
+        if self.peek() == Some(c) {
+            self.pos += c.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), String> {
+        if self.eat(c) {
+            return Ok(());
+        }
+        match self.peek() {
+            Some(found) => Err(format!("expected `{c}`, found `{found}`")),
+            None => Err(format!("expected `{c}` before the end of expression")),
+        }
+    }
+
+    fn skip_spaces(&mut self) {
+        while let Some(c) = self.peek().filter(|c| c.is_whitespace()) {
+            self.pos += c.len_utf8();
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.text[self.pos..].chars().next()
+    }
+
+    fn unexpected(&self, c: char) -> String {
+        format!("unexpected `{c}` at column {}", self.pos + 1)
+    }
+}
+
+/// Evaluates the `[vars]` table of a parsed scene and the expressions in
+/// its numeric fields, replacing them with numbers. The `vars` table is
+/// removed, so variables are local to the file that defines them.
+///
+/// Numeric fields are found with the JSON Schema of `SceneFile`; strings
+/// anywhere else, such as material names, are left alone. Returns whether
+/// the scene was changed.
+pub(crate) fn evaluate_scene_expressions(value: &mut Value) -> Result<bool, SceneError> {
+    let Some(scene) = value.as_object_mut() else {
+        return Ok(false);
+    };
+    let mut vars = Vars::default();
+    let had_vars = scene.contains_key("vars");
+    if let Some(defs) = scene.remove("vars") {
+        let Value::Object(defs) = defs else {
+            return Err(invalid("vars".to_string(), "must be a table".to_string()));
+        };
+        vars.defs = defs;
+        let names: Vec<String> = vars.defs.keys().cloned().collect();
+        for name in names {
+            vars.get(&name)
+                .map_err(|message| invalid(format!("vars.{name}"), message))?;
+        }
+    }
+
+    static SCHEMA: OnceLock<schemars::Schema> = OnceLock::new();
+    let root = SCHEMA
+        .get_or_init(|| schemars::schema_for!(SceneFile))
+        .as_value();
+    let mut walker = Walker {
+        defs: &root["$defs"],
+        vars: &vars.values,
+        replaced: false,
+    };
+    let mut path = String::new();
+    for (key, value) in scene.iter_mut() {
+        if let Some(schema) = walker.property(root, key) {
+            path.clear();
+            path.push_str(key);
+            walker.walk(value, schema, &mut path)?;
+        }
+    }
+    Ok(had_vars || walker.replaced)
+}
+
+/// The variables of a scene, evaluated on first use so that they may refer
+/// to each other in any order.
+#[derive(Default)]
+struct Vars {
+    defs: Map<String, Value>,
+    values: BTreeMap<String, f64>,
+    resolving: Vec<String>,
+}
+
+impl Vars {
+    fn get(&mut self, name: &str) -> Result<f64, String> {
+        if let Some(value) = self.values.get(name) {
+            return Ok(*value);
+        }
+        if let Some(start) = self.resolving.iter().position(|n| n == name) {
+            let mut cycle: Vec<String> = self.resolving[start..]
+                .iter()
+                .map(|n| format!("${n}"))
+                .collect();
+            cycle.push(format!("${name}"));
+            return Err(format!("variable cycle: {}", cycle.join(" -> ")));
+        }
+        if self.resolving.len() >= MAX_DEPTH {
+            return Err(format!(
+                "variables refer to each other more than {MAX_DEPTH} levels deep"
+            ));
+        }
+        let value = match self.defs.get(name) {
+            Some(Value::Number(n)) => n.as_f64().unwrap_or(f64::NAN),
+            Some(Value::String(text)) => {
+                let text = text.clone();
+                self.resolving.push(name.to_string());
+                let res = evaluate_with(&text, &mut |n| self.get(n));
+                self.resolving.pop();
+                res?
+            }
+            Some(_) => return Err(format!("`${name}` must be a number or an expression")),
+            None => return Err(format!("unknown variable `${name}`")),
+        };
+        self.values.insert(name.to_string(), value);
+        Ok(value)
+    }
+}
+
+/// Walks a scene value alongside its JSON Schema.
+struct Walker<'a> {
+    defs: &'a Value,
+    vars: &'a BTreeMap<String, f64>,
+    replaced: bool,
+}
+
+impl<'a> Walker<'a> {
+    fn walk(
+        &mut self,
+        value: &mut Value,
+        schema: &'a Value,
+        path: &mut String,
+    ) -> Result<(), SceneError> {
+        let schema = self.resolve(schema);
+        match value {
+            Value::String(text) => {
+                if let Some(integer) = self.variants(schema).into_iter().find_map(numeric_type) {
+                    let number = evaluate_expression(text, self.vars)
+                        .map_err(|message| invalid(path.clone(), message))?;
+                    *value = to_json_number(number, integer)
+                        .map_err(|message| invalid(path.clone(), message))?;
+                    self.replaced = true;
+                }
+            }
+            Value::Array(items) => {
+                let variants = self.variants(schema);
+                if let Some(items_schema) = variants.into_iter().find_map(|s| s.get("items")) {
+                    let len = path.len();
+                    for (i, item) in items.iter_mut().enumerate() {
+                        path.push_str(&format!("[{i}]"));
+                        self.walk(item, items_schema, path)?;
+                        path.truncate(len);
+                    }
+                }
+            }
+            Value::Object(map) => {
+                let len = path.len();
+                for (key, item) in map.iter_mut() {
+                    if let Some(item_schema) = self.property(schema, key) {
+                        path.push('.');
+                        path.push_str(key);
+                        self.walk(item, item_schema, path)?;
+                        path.truncate(len);
+                    }
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Finds the schema of a property in an object schema, including the
+    /// alternatives of tagged enums and flattened fields.
+    fn property(&self, schema: &'a Value, key: &str) -> Option<&'a Value> {
+        let schema = self.resolve(schema);
+        if let Some(property) = schema.get("properties").and_then(|p| p.get(key)) {
+            return Some(property);
+        }
+        for keyword in ["oneOf", "anyOf", "allOf"] {
+            let alternatives = schema.get(keyword).and_then(Value::as_array);
+            for alternative in alternatives.into_iter().flatten() {
+                if let Some(property) = self.property(alternative, key) {
+                    return Some(property);
+                }
+            }
+        }
+        schema.get("additionalProperties").filter(|s| s.is_object())
+    }
+
+    /// Returns a schema followed by its `anyOf` and `oneOf` alternatives,
+    /// such as the number and array forms of an untagged enum.
+    fn variants(&self, schema: &'a Value) -> Vec<&'a Value> {
+        let alternatives = ["anyOf", "oneOf"]
+            .into_iter()
+            .filter_map(|keyword| schema.get(keyword).and_then(Value::as_array))
+            .flatten()
+            .map(|alternative| self.resolve(alternative));
+        std::iter::once(schema).chain(alternatives).collect()
+    }
+
+    fn resolve(&self, schema: &'a Value) -> &'a Value {
+        match schema
+            .get("$ref")
+            .and_then(Value::as_str)
+            .and_then(|r| r.strip_prefix("#/$defs/"))
+        {
+            Some(name) => &self.defs[name],
+            None => schema,
+        }
+    }
+}
+
+/// Returns `Some(true)` for integer schemas and `Some(false)` for other
+/// numeric schemas.
+fn numeric_type(schema: &Value) -> Option<bool> {
+    let types: Vec<&str> = match schema.get("type")? {
+        Value::String(t) => vec![t.as_str()],
+        Value::Array(ts) => ts.iter().filter_map(Value::as_str).collect(),
+        _ => return None,
+    };
+    if types.contains(&"string") {
+        return None;
+    }
+    if types.contains(&"integer") {
+        Some(true)
+    } else if types.contains(&"number") {
+        Some(false)
+    } else {
+        None
+    }
+}
+
+fn to_json_number(number: f64, integer: bool) -> Result<Value, String> {
+    if !integer {
+        return Ok(number.into());
+    }
+    if number.fract() != 0.0 {
+        return Err(format!("must be a whole number, got {number}"));
+    }
+    Ok((number as i64).into())
+}
+
+fn invalid(path: String, message: String) -> SceneError {
+    SceneError::InvalidExpression { path, message }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json::parse_json_scene_from_str;
+    use crate::toml::parse_toml_scene_from_str;
+    use scene_types::{MaterialDef, TransformDef};
+    use std::f64::consts::FRAC_PI_4;
+
+    #[test]
+    fn evaluate_expression_ok() {
+        let vars = BTreeMap::from([("radius".to_string(), 1.5)]);
+        let eval = |text: &str| evaluate_expression(text, &vars).unwrap();
+
+        assert_eq!(eval("$radius * 2"), 3.0);
+        assert_eq!(eval("pi/4"), FRAC_PI_4);
+        assert_eq!(eval("deg(45)"), FRAC_PI_4);
+        assert_eq!(eval("-(1 + 2) * -3 % 5"), 4.0);
+        assert_eq!(eval(" 2 - 3 - 4 "), -5.0);
+        assert_eq!(eval("max(1, $radius) + min(1, 2)"), 2.5);
+        assert_eq!(eval("sqrt(16) + abs(-1) + cos(0)"), 6.0);
+        assert_eq!(eval("1.5e2 + 1e-1"), 150.1);
+        assert_eq!(eval("1\u{a0}+\u{3000}2"), 3.0);
+    }
+
+    #[test]
+    fn evaluate_expression_yields_error() {
+        let vars = BTreeMap::new();
+        let eval = |text: &str| evaluate_expression(text, &vars).unwrap_err();
+
+        assert_eq!(eval("$radius"), "unknown variable `$radius`");
+        assert_eq!(eval("deg(1, 2)"), "`deg` takes 1 argument(s), got 2");
+        assert_eq!(eval("log(1)"), "unknown function `log`");
+        assert_eq!(eval("e"), "unknown constant `e`");
+        assert_eq!(eval("(1 + 2"), "expected `)` before the end of expression");
+        assert_eq!(eval("1 2"), "unexpected `2` at column 3");
+        assert_eq!(eval("1 / 0"), "`1 / 0` is not a finite number");
+        assert_eq!(eval(""), "unexpected end of expression");
+        assert_eq!(
+            eval(&"(".repeat(200_000)),
+            "expression is nested more than 128 levels deep"
+        );
+        assert_eq!(
+            eval(&"-".repeat(200_000)),
+            "expression is nested more than 128 levels deep"
+        );
+    }
+
+    const SCENE: &str = r#"
+        version = 2
+
+        [vars]
+        diameter = "$radius * 2"
+        radius = 1.5
+        gray = 128
+
+        [camera]
+        position = [0, "$diameter", -5]
+        fov = "90 - 30"
+
+        [materials.matte]
+        shininess = "$radius * 10"
+
+        [[lights]]
+        type = "point"
+        position = [-10, 10, -10]
+        intensity = [1, 1, 1]
+
+        [[objects]]
+        type = "sphere"
+        position = [0, 0, 0]
+        radius = "$radius"
+        material = "matte"
+        color = ["$gray", "$gray * 2 - 1", 0]
+        transform = [
+            { rotate_y = "pi/4" },
+            { rotate_x = "deg(45)" },
+            { shear = { xy = 0, xz = "1/2", yx = 0, yz = 0, zx = 0, zy = 0 } },
+        ]
+    "#;
+
+    #[test]
+    fn evaluate_scene_expressions_ok() {
+        let scene = parse_toml_scene_from_str(SCENE).unwrap();
+        assert_eq!(scene.camera.position, [0.0, 3.0, -5.0]);
+        assert_eq!(scene.camera.fov, 60.0);
+        assert_eq!(scene.materials["matte"].shininess, Some(15.0));
+        assert_eq!(
+            scene.objects[0].material,
+            MaterialDef::Named("matte".to_string())
+        );
+        assert_eq!(scene.objects[0].color, [128, 255, 0]);
+        assert_eq!(
+            scene.objects[0].transform[0],
+            TransformDef::RotateY(std::f32::consts::FRAC_PI_4)
+        );
+        assert_eq!(
+            scene.objects[0].transform[1],
+            TransformDef::RotateX(std::f32::consts::FRAC_PI_4)
+        );
+        let TransformDef::Shear(shear) = &scene.objects[0].transform[2] else {
+            panic!("expected a shear");
+        };
+        assert_eq!(shear.xz, 0.5);
+
+        let json = r#"{
+            "version": 2,
+            "vars": { "r": 2 },
+            "lights": [{ "type": "point", "position": [0, 0, 0], "intensity": ["$r", 1, 1] }],
+            "objects": [{ "type": "sphere", "position": [0, 0, 0], "radius": "$r / 4" }]
+        }"#;
+        let scene = parse_json_scene_from_str(json).unwrap();
+        assert_eq!(scene.lights[0].intensity, [2.0, 1.0, 1.0]);
+        assert_eq!(
+            scene.objects[0].shape,
+            scene_types::ShapeDef::Sphere { radius: 0.5 }
+        );
+    }
+
+    #[test]
+    fn evaluate_scene_expressions_yields_error() {
+        let expression_error = |source: &str| match parse_toml_scene_from_str(source) {
+            Err(SceneError::InvalidExpression { path, message }) => (path, message),
+            other => panic!("expected an expression error, got {other:?}"),
+        };
+
+        let source = SCENE.replace("radius = 1.5", "radius = \"$diameter / 2\"");
+        let (path, message) = expression_error(&source);
+        assert_eq!(path, "vars.diameter");
+        assert_eq!(message, "variable cycle: $diameter -> $radius -> $diameter");
+
+        let source = SCENE.replace("\"$radius\"", "\"$radius * \"");
+        let (path, message) = expression_error(&source);
+        assert_eq!(path, "objects[0].radius");
+        assert_eq!(message, "unexpected end of expression");
+
+        let source = SCENE.replace("\"$gray\", ", "\"$gray / 3\", ");
+        let (path, message) = expression_error(&source);
+        assert_eq!(path, "objects[0].color[0]");
+        assert_eq!(message, "must be a whole number, got 42.666666666666664");
+
+        let chain: String = (0..200)
+            .map(|i| format!("v{i} = \"$v{} + 1\"\n", i + 1))
+            .collect();
+        let source = SCENE.replace("gray = 128", &format!("gray = 128\n{chain}v200 = 0"));
+        let (_, message) = expression_error(&source);
+        assert_eq!(
+            message,
+            "variables refer to each other more than 128 levels deep"
+        );
+
+        let source = SCENE.replace("gray = 128", "gray = [128]");
+        let (path, _) = expression_error(&source);
+        assert_eq!(path, "vars.gray");
+
+        let err = parse_toml_scene_from_str(&SCENE.replace("\"$radius\"", "\"$size\""));
+        assert_eq!(
+            err.unwrap_err().to_string(),
+            "invalid expression at `objects[0].radius`: unknown variable `$size`"
+        );
+    }
+}
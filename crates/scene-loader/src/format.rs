@@ -0,0 +1,85 @@
+use std::path::Path;
+
+/// A scene file syntax understood by `load_scene`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SceneFormat {
+    /// The native TOML format, with `include` support when loaded from disk.
+    Toml,
+    /// The same schema as TOML, written as JSON.
+    Json,
+    /// The YAML format of "The Ray Tracer Challenge".
+    Yaml,
+}
+
+impl SceneFormat {
+    /// Selects a format from a file extension (case insensitive).
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        match extension.to_ascii_lowercase().as_str() {
+            "toml" => Some(SceneFormat::Toml),
+            "json" => Some(SceneFormat::Json),
+            "yaml" | "yml" => Some(SceneFormat::Yaml),
+            _ => None,
+        }
+    }
+
+    /// Selects a format from the extension of `path`.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Option<Self> {
+        path.as_ref()
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .and_then(SceneFormat::from_extension)
+    }
+
+    /// Guesses the format from the first line that is not blank or a
+    /// comment: `{` starts a JSON object, `-` a YAML list (or a `---`
+    /// document marker) and anything else is taken as TOML.
+    pub fn sniff(text: &str) -> Self {
+        let first = text
+            .trim_start_matches('\u{feff}')
+            .lines()
+            .map(str::trim_start)
+            .find(|line| !line.is_empty() && !line.starts_with('#'));
+        match first.and_then(|line| line.chars().next()) {
+            Some('{') => SceneFormat::Json,
+            Some('-') => SceneFormat::Yaml,
+            _ => SceneFormat::Toml,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scene_format_from_path_ok() {
+        assert_eq!(SceneFormat::from_path("a.toml"), Some(SceneFormat::Toml));
+        assert_eq!(SceneFormat::from_path("a/b.JSON"), Some(SceneFormat::Json));
+        assert_eq!(SceneFormat::from_path("a.yml"), Some(SceneFormat::Yaml));
+        assert_eq!(SceneFormat::from_path("a.yaml"), Some(SceneFormat::Yaml));
+        assert_eq!(SceneFormat::from_path("a.txt"), None);
+        assert_eq!(SceneFormat::from_path("scene"), None);
+    }
+
+    #[test]
+    fn scene_format_sniff_ok() {
+        assert_eq!(
+            SceneFormat::sniff("\n  { \"camera\": {} }"),
+            SceneFormat::Json
+        );
+        assert_eq!(
+            SceneFormat::sniff("# book scene\n\n- add: camera\n"),
+            SceneFormat::Yaml
+        );
+        assert_eq!(SceneFormat::sniff("---\n- add: light\n"), SceneFormat::Yaml);
+        assert_eq!(
+            SceneFormat::sniff("# comment\n[camera]\nfov = 60.0\n"),
+            SceneFormat::Toml
+        );
+        assert_eq!(
+            SceneFormat::sniff("include = [\"a.toml\"]"),
+            SceneFormat::Toml
+        );
+        assert_eq!(SceneFormat::sniff(""), SceneFormat::Toml);
+    }
+}
@@ -0,0 +1,480 @@
+use crate::error::SceneError;
+use crate::migrate::{MigrationWarning, from_toml_str};
+use crate::validate::{Segment, SourceLocation, locate_in_source, validate_scene_with};
+use scene_types::{
+    AmbientDef, AnimationDef, CURRENT_VERSION, CameraDef, LightDef, NamedMaterialDef, ObjectDef,
+    SceneFile,
+};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The contents of a single TOML file, which may be a partial scene.
+///
+/// Every section is optional so that shared material libraries, lighting
+/// rigs and prop sets can live in their own files. Each file is upgraded to
+/// the current version on its own.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SceneFragment {
+    #[serde(rename = "version")]
+    _version: u32,
+    #[serde(default)]
+    include: Vec<String>,
+    camera: Option<CameraDef>,
+    ambient: Option<AmbientDef>,
+    #[serde(default)]
+    materials: BTreeMap<String, NamedMaterialDef>,
+    #[serde(default)]
+    lights: Vec<LightDef>,
+    #[serde(default)]
+    objects: Vec<ObjectDef>,
+    animation: Option<AnimationDef>,
+}
+
+/// A file read while composing a scene.
+struct SourceFile {
+    path: PathBuf,
+    text: String,
+}
+
+/// Which file, by index into `Composer::files`, each part of the composed
+/// scene came from. Lights and objects also keep their index in that file.
+#[derive(Default)]
+struct Origins {
+    camera: Option<usize>,
+    ambient: Option<usize>,
+    animation: Option<usize>,
+    materials: BTreeMap<String, usize>,
+    lights: Vec<(usize, usize)>,
+    objects: Vec<(usize, usize)>,
+}
+
+#[derive(Default)]
+struct Composer {
+    /// Canonical and display paths of the files being loaded, root first.
+    stack: Vec<(PathBuf, PathBuf)>,
+    /// Canonical paths of the files already merged, which are not merged
+    /// again when included a second time.
+    loaded: Vec<PathBuf>,
+    files: Vec<SourceFile>,
+    warnings: Vec<MigrationWarning>,
+    origins: Origins,
+    camera: Option<CameraDef>,
+    ambient: Option<AmbientDef>,
+    materials: BTreeMap<String, NamedMaterialDef>,
+    lights: Vec<LightDef>,
+    objects: Vec<ObjectDef>,
+    animation: Option<AnimationDef>,
+}
+
+/// Loads a TOML scene file together with the files it includes.
+///
+/// A file lists other files in a top-level `include` array; relative paths
+/// are resolved against the directory of the including file. Included
+/// files are merged in order before the including file, so that:
+/// - lights and objects are appended,
+/// - materials with the same name are replaced,
+/// - a `camera`, `ambient` or `animation` table replaces an earlier one.
+///
+/// A file is merged only once, where it is first included, so that two
+/// files sharing a library do not duplicate its lights and objects.
+///
+/// Animation track targets refer to the composed scene, whichever file the
+/// animation is written in.
+///
+/// Errors in an included file and include cycles report the chain of
+/// files that led to them. Validation issues name the file they are in.
+pub fn load_toml_scene(path: &Path) -> Result<SceneFile, SceneError> {
+    load_toml_scene_with_warnings(path).map(|(scene, _)| scene)
+}
+
+/// Loads a TOML scene like `load_toml_scene`, also returning the changes
+/// made to upgrade each file from an older version of the format.
+pub(crate) fn load_toml_scene_with_warnings(
+    path: &Path,
+) -> Result<(SceneFile, Vec<MigrationWarning>), SceneError> {
+    let mut composer = Composer::default();
+    composer.load(path)?;
+    composer.finish()
+}
+
+impl Composer {
+    fn load(&mut self, path: &Path) -> Result<(), SceneError> {
+        let canonical = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        if self.stack.iter().any(|(c, _)| *c == canonical) {
+            let mut chain: Vec<PathBuf> = self.stack.iter().map(|(_, d)| d.clone()).collect();
+            chain.push(path.to_path_buf());
+            return Err(SceneError::IncludeCycle(chain));
+        }
+        if self.loaded.contains(&canonical) {
+            return Ok(());
+        }
+        self.stack.push((canonical, path.to_path_buf()));
+
+        let fragment = self.read(path).map_err(|err| self.in_include(err))?;
+        let file = self.files.len() - 1;
+
+        let dir = path.parent().unwrap_or(Path::new(""));
+        for include in &fragment.include {
+            self.load(&dir.join(include))?;
+        }
+        self.merge(fragment, file);
+
+        if let Some((canonical, _)) = self.stack.pop() {
+            self.loaded.push(canonical);
+        }
+        Ok(())
+    }
+
+    fn read(&mut self, path: &Path) -> Result<SceneFragment, SceneError> {
+        let text = fs::read_to_string(path)?;
+        let (fragment, warnings) = from_toml_str::<SceneFragment>(&text)?;
+        self.warnings
+            .extend(warnings.into_iter().map(|warning| MigrationWarning {
+                file: Some(path.to_path_buf()),
+                ..warning
+            }));
+        self.files.push(SourceFile {
+            path: path.to_path_buf(),
+            text,
+        });
+        Ok(fragment)
+    }
+
+    /// Attaches the current include chain to an error raised in an
+    /// included file. Errors in the root file are returned as they are.
+    fn in_include(&self, err: SceneError) -> SceneError {
+        if self.stack.len() < 2 {
+            return err;
+        }
+        SceneError::Included {
+            chain: self.stack.iter().map(|(_, d)| d.clone()).collect(),
+            source: Box::new(err),
+        }
+    }
+
+    fn merge(&mut self, fragment: SceneFragment, file: usize) {
+        if fragment.camera.is_some() {
+            self.camera = fragment.camera;
+            self.origins.camera = Some(file);
+        }
+        if fragment.ambient.is_some() {
+            self.ambient = fragment.ambient;
+            self.origins.ambient = Some(file);
+        }
+        if fragment.animation.is_some() {
+            self.animation = fragment.animation;
+            self.origins.animation = Some(file);
+        }
+        for (name, material) in fragment.materials {
+            self.origins.materials.insert(name.clone(), file);
+            self.materials.insert(name, material);
+        }
+        let lights = fragment.lights.len();
+        self.origins.lights.extend((0..lights).map(|i| (file, i)));
+        self.lights.extend(fragment.lights);
+        let objects = fragment.objects.len();
+        self.origins.objects.extend((0..objects).map(|i| (file, i)));
+        self.objects.extend(fragment.objects);
+    }
+
+    /// Builds the composed scene. Sections that no file defines take their
+    /// default values.
+    fn finish(self) -> Result<(SceneFile, Vec<MigrationWarning>), SceneError> {
+        let scene = SceneFile {
+            version: CURRENT_VERSION,
+            camera: self.camera.clone().unwrap_or_default(),
+            ambient: self.ambient.clone().unwrap_or_default(),
+            materials: self.materials.clone(),
+            lights: self.lights.clone(),
+            objects: self.objects.clone(),
+            animation: self.animation.clone(),
+        };
+        validate_scene_with(&scene, |path| self.locate(path))?;
+        Ok((scene, self.warnings))
+    }
+
+    /// Finds the file and position of the value at a path of the composed
+    /// scene. Light and object indices are mapped back into their file.
+    fn locate(&self, path: &[Segment<'_>]) -> (Option<PathBuf>, Option<SourceLocation>) {
+        let mut local: Vec<Segment<'_>> = path.to_vec();
+        let file = match path {
+            [Segment::Key("camera"), ..] => self.origins.camera,
+            [Segment::Key("ambient"), ..] => self.origins.ambient,
+            [Segment::Key("animation"), ..] => self.origins.animation,
+            [Segment::Key("materials"), Segment::Key(name), ..] => {
+                self.origins.materials.get(*name).copied()
+            }
+            [
+                Segment::Key(key @ ("lights" | "objects")),
+                Segment::Index(i),
+                ..,
+            ] => {
+                let origins = match *key {
+                    "lights" => &self.origins.lights,
+                    _ => &self.origins.objects,
+                };
+                origins.get(*i).map(|&(file, j)| {
+                    local[1] = Segment::Index(j);
+                    file
+                })
+            }
+            _ => Some(0),
+        };
+        match file.and_then(|file| self.files.get(file)) {
+            Some(source) => (
+                Some(source.path.clone()),
+                locate_in_source(&source.text, &local),
+            ),
+            None => (None, None),
+        }
+    }
+}
+
+/// Formats an include chain, innermost file first.
+pub(crate) fn describe_include_chain(chain: &[PathBuf]) -> String {
+    let mut files = chain.iter().rev().map(|path| path.display().to_string());
+    let mut res = format!("in {}", files.next().unwrap_or_default());
+    for file in files {
+        res.push_str(&format!(", included from {file}"));
+    }
+    res
+}
+
+/// Formats an include cycle from the root file onwards.
+pub(crate) fn describe_include_cycle(chain: &[PathBuf]) -> String {
+    chain
+        .iter()
+        .map(|path| path.display().to_string())
+        .collect::<Vec<_>>()
+        .join(" -> ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const SCENE: &str = r#"
+        version = 2
+        include = ["shared/materials.toml", "shared/rig.toml"]
+
+        [camera]
+        position = [0, 0, -5]
+        target = [0, 0, 0]
+        fov = 60.0
+
+        [materials.matte]
+        shininess = 5.0
+
+        [[objects]]
+        type = "sphere"
+        position = [0, 0, 0]
+        radius = 1.0
+        material = "matte"
+        color = [255, 0, 0]
+    "#;
+
+    const MATERIALS: &str = r#"
+        version = 2
+
+        [materials.matte]
+        specular-coefficient = 0.0
+
+        [materials.shiny]
+        shininess = 300.0
+    "#;
+
+    // written before scenes had a version
+    const RIG: &str = r#"
+        include = ["materials.toml"]
+
+        [ambient]
+        intensity = 0.5
+        color = [255, 255, 255]
+
+        [[lights]]
+        type = "point"
+        position = [-10, 10, -10]
+        intensity = 1.0
+        color = [255, 255, 255]
+
+        [[objects]]
+        type = "sphere"
+        position = [0, -101, 0]
+        radius = 100.0
+        material = "shiny"
+        color = [200, 200, 200]
+    "#;
+
+    fn write_files(files: &[(&str, &str)]) -> TempDir {
+        let dir = tempfile::tempdir().expect("create temp dir");
+        for (name, text) in files {
+            let path = dir.path().join(name);
+            fs::create_dir_all(path.parent().unwrap()).expect("create dir");
+            fs::write(path, text).expect("write file");
+        }
+        dir
+    }
+
+    #[test]
+    fn load_toml_scene_ok() {
+        let dir = write_files(&[
+            ("scene.toml", SCENE),
+            ("shared/materials.toml", MATERIALS),
+            ("shared/rig.toml", RIG),
+        ]);
+        let (scene, warnings) =
+            load_toml_scene_with_warnings(&dir.path().join("scene.toml")).unwrap();
+
+        assert_eq!(scene.camera.fov, 60.0);
+        assert_eq!(scene.ambient.intensity, 0.5);
+        assert_eq!(scene.lights.len(), 1);
+        // included objects come first
+        assert_eq!(scene.objects.len(), 2);
+        assert_eq!(scene.objects[0].position, [0.0, -101.0, 0.0]);
+        assert_eq!(scene.objects[1].position, [0.0, 0.0, 0.0]);
+        // the including file replaces a material of the same name
+        assert_eq!(scene.materials.len(), 2);
+        assert_eq!(scene.materials["matte"].specular_coeff, None);
+        assert_eq!(scene.materials["matte"].shininess, Some(5.0));
+        // only the file of an older version is upgraded
+        assert_eq!(scene.lights[0].intensity, [1.0, 1.0, 1.0]);
+        assert_eq!(warnings.len(), 2);
+        assert_eq!(warnings[1].path, "lights[0]");
+        assert!(
+            warnings[1]
+                .file
+                .as_ref()
+                .unwrap()
+                .ends_with("shared/rig.toml")
+        );
+    }
+
+    #[test]
+    fn load_toml_scene_shared_include_ok() {
+        // both props include the lamp, whose light and object are merged once
+        let lamp = r#"
+            version = 2
+
+            [[lights]]
+            type = "point"
+            position = [0, 5, 0]
+            intensity = [1, 1, 1]
+
+            [[objects]]
+            type = "sphere"
+            position = [0, 5, 0]
+            radius = 0.1
+        "#;
+        let dir = write_files(&[
+            (
+                "scene.toml",
+                "version = 2\ninclude = [\"desk.toml\", \"shelf/shelf.toml\"]\n",
+            ),
+            ("desk.toml", "version = 2\ninclude = [\"lib/lamp.toml\"]\n"),
+            (
+                "shelf/shelf.toml",
+                "version = 2\ninclude = [\"../lib/lamp.toml\"]\n",
+            ),
+            ("lib/lamp.toml", lamp),
+        ]);
+        let scene = load_toml_scene(&dir.path().join("scene.toml")).unwrap();
+        assert_eq!(scene.lights.len(), 1);
+        assert_eq!(scene.lights[0].position, [0.0, 5.0, 0.0]);
+        assert_eq!(scene.objects.len(), 1);
+    }
+
+    #[test]
+    fn load_toml_scene_yields_error() {
+        let dir = write_files(&[
+            ("scene.toml", SCENE),
+            (
+                "shared/materials.toml",
+                "[materials.matte]\nUNKNOWN = 1.0\n",
+            ),
+            ("shared/rig.toml", RIG),
+        ]);
+        let err = load_toml_scene(&dir.path().join("scene.toml")).unwrap_err();
+        let SceneError::Included { chain, source } = &err else {
+            panic!("expected an include error, got {err:?}");
+        };
+        assert_eq!(chain.len(), 2);
+        assert!(chain[1].ends_with("shared/materials.toml"));
+        assert!(matches!(**source, SceneError::InvalidScene(_)));
+        assert!(err.to_string().starts_with(&format!(
+            "in {}, included from {}: ",
+            chain[1].display(),
+            chain[0].display()
+        )));
+
+        let dir = write_files(&[("scene.toml", SCENE), ("shared/rig.toml", RIG)]);
+        let err = load_toml_scene(&dir.path().join("scene.toml")).unwrap_err();
+        let SceneError::Included { chain, source } = &err else {
+            panic!("expected an include error, got {err:?}");
+        };
+        assert!(chain[1].ends_with("materials.toml"));
+        assert!(matches!(**source, SceneError::InvalidFile(_)));
+    }
+
+    #[test]
+    fn load_toml_scene_cycle_yields_error() {
+        let dir = write_files(&[
+            ("scene.toml", SCENE),
+            ("shared/materials.toml", "include = [\"rig.toml\"]\n"),
+            ("shared/rig.toml", RIG),
+        ]);
+        let err = load_toml_scene(&dir.path().join("scene.toml")).unwrap_err();
+        let SceneError::IncludeCycle(chain) = &err else {
+            panic!("expected an include cycle, got {err:?}");
+        };
+        let names: Vec<_> = chain.iter().map(|p| p.file_name().unwrap()).collect();
+        assert_eq!(
+            names,
+            ["scene.toml", "materials.toml", "rig.toml", "materials.toml"]
+        );
+        assert!(err.to_string().starts_with("include cycle: "));
+    }
+
+    #[test]
+    fn load_toml_scene_validation_yields_error() {
+        let dir = write_files(&[
+            ("scene.toml", SCENE),
+            ("shared/materials.toml", MATERIALS),
+            (
+                "shared/rig.toml",
+                &RIG.replace("radius = 100.0", "radius = -100.0"),
+            ),
+        ]);
+        let err = load_toml_scene(&dir.path().join("scene.toml")).unwrap_err();
+        let SceneError::Validation(issues) = err else {
+            panic!("expected a validation error");
+        };
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].path, "objects[0].radius");
+        assert!(
+            issues[0]
+                .file
+                .as_ref()
+                .unwrap()
+                .ends_with("shared/rig.toml")
+        );
+        assert_eq!(
+            issues[0].location,
+            Some(SourceLocation {
+                line: 17,
+                column: 18
+            })
+        );
+
+        let dir = write_files(&[("scene.toml", "[camera]\nposition = [0, 0, 0]\n")]);
+        let err = load_toml_scene(&dir.path().join("scene.toml")).unwrap_err();
+        let SceneError::Validation(issues) = err else {
+            panic!("expected a validation error");
+        };
+        let paths: Vec<&str> = issues.iter().map(|i| i.path.as_str()).collect();
+        assert_eq!(paths, ["camera.target", "objects"]);
+    }
+}
@@ -0,0 +1,78 @@
+use crate::error::SceneError;
+use crate::migrate::{MigrationWarning, from_json_str};
+use crate::validate::validate_scene;
+use scene_types::SceneFile;
+
+/// Parses and validates a JSON scene with the same schema as TOML scenes.
+///
+/// Scenes written for an older version of the format are upgraded first.
+pub fn parse_json_scene_from_str(json_str: &str) -> Result<SceneFile, SceneError> {
+    parse_json_scene_with_warnings(json_str).map(|(scene, _)| scene)
+}
+
+pub(crate) fn parse_json_scene_with_warnings(
+    json_str: &str,
+) -> Result<(SceneFile, Vec<MigrationWarning>), SceneError> {
+    let (scene, warnings) = from_json_str::<SceneFile>(json_str)?;
+    validate_scene(&scene)?;
+    Ok((scene, warnings))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use scene_types::{MaterialDef, TransformDef};
+
+    const SCENE: &str = r#"{
+        "version": 2,
+        "camera": { "position": [0, 0, -5], "target": [0, 0, 0], "fov": 60.0 },
+        "ambient": { "intensity": 0.4, "color": [255, 255, 255] },
+        "materials": { "matte": { "specular-coefficient": 0.0 } },
+        "lights": [
+            { "type": "point", "position": [-10, 10, -10], "intensity": [0.5, 0.5, 0.5] }
+        ],
+        "objects": [
+            {
+                "type": "sphere",
+                "position": [0, 0, 0],
+                "radius": 1.0,
+                "material": "matte",
+                "color": [136, 8, 8],
+                "transform": [{ "scale": [1, 2, 1] }]
+            },
+            {
+                "type": "sphere",
+                "position": [0, 0, 3],
+                "radius": 2.0,
+                "material": { "shininess": 100.0 },
+                "color": [8, 136, 8]
+            }
+        ]
+    }"#;
+
+    #[test]
+    fn parse_json_scene_from_str_ok() {
+        let scene = parse_json_scene_from_str(SCENE).unwrap();
+        assert_eq!(scene.camera.position, [0.0, 0.0, -5.0]);
+        assert_eq!(scene.materials["matte"].specular_coeff, Some(0.0));
+        assert_eq!(scene.lights.len(), 1);
+        assert_eq!(scene.objects.len(), 2);
+        assert_eq!(
+            scene.objects[0].material,
+            MaterialDef::Named("matte".to_string())
+        );
+        assert_eq!(
+            scene.objects[0].transform,
+            vec![TransformDef::Scale([1.0, 2.0, 1.0])]
+        );
+    }
+
+    #[test]
+    fn parse_json_scene_from_str_yields_error() {
+        let err = parse_json_scene_from_str(&SCENE.replace("\"fov\"", "\"UNKNOWN\""));
+        assert!(matches!(err, Err(SceneError::InvalidJson(_))));
+
+        let err = parse_json_scene_from_str(&SCENE.replace("\"radius\": 2.0", "\"radius\": 0"));
+        assert!(matches!(err, Err(SceneError::Validation(_))));
+    }
+}
@@ -1,19 +1,89 @@
+pub mod animation;
+pub mod builder;
+pub mod cli;
 pub mod error;
+pub mod expr;
+pub mod format;
+pub mod include;
+pub mod json;
+pub mod material;
+pub mod migrate;
 pub mod toml;
+pub mod validate;
+pub mod watch;
+pub mod writer;
+pub mod yaml;
 
-pub fn load_scene(path: &str) -> Result<scene_types::SceneFile, error::SceneError> {
-    use crate::toml::parse_toml_scene_from_str;
-    use std::fs;
+use crate::error::SceneError;
+use crate::format::SceneFormat;
+use crate::migrate::MigrationWarning;
+use scene_types::SceneFile;
+use std::fs;
+use std::io::Read;
+use std::path::Path;
 
-    let text = fs::read_to_string(&path)?;
-    let scene = parse_toml_scene_from_str(&text)?;
-    Ok(scene)
+/// Loads and validates a scene file.
+///
+/// The parser is selected by the file extension, or by sniffing the
+/// contents when the extension is unknown. TOML scenes may include other
+/// TOML files. Scenes written for an older version of the format are
+/// upgraded; use `load_scene_with_warnings` to learn what was changed.
+pub fn load_scene<P: AsRef<Path>>(path: P) -> Result<SceneFile, SceneError> {
+    load_scene_with_warnings(path).map(|(scene, _)| scene)
+}
+
+/// Loads a scene file like `load_scene`, also returning the changes made
+/// to upgrade it from an older version of the format.
+pub fn load_scene_with_warnings<P: AsRef<Path>>(
+    path: P,
+) -> Result<(SceneFile, Vec<MigrationWarning>), SceneError> {
+    let path = path.as_ref();
+    let format = match SceneFormat::from_path(path) {
+        Some(format) => format,
+        None => SceneFormat::sniff(&fs::read_to_string(path)?),
+    };
+    match format {
+        SceneFormat::Toml => include::load_toml_scene_with_warnings(path),
+        _ => parse_scene_from_str_with_warnings(&fs::read_to_string(path)?, format),
+    }
+}
+
+/// Reads and validates a scene from any reader, such as stdin or an
+/// in-memory buffer. The format is sniffed from the contents when `format`
+/// is `None`.
+///
+/// Without a file to resolve them against, TOML `include`s are rejected.
+pub fn load_scene_from_reader<R: Read>(
+    mut reader: R,
+    format: Option<SceneFormat>,
+) -> Result<SceneFile, SceneError> {
+    let mut text = String::new();
+    reader.read_to_string(&mut text)?;
+    let format = format.unwrap_or_else(|| SceneFormat::sniff(&text));
+    parse_scene_from_str(&text, format)
+}
+
+/// Parses and validates a scene written in the given format.
+pub fn parse_scene_from_str(text: &str, format: SceneFormat) -> Result<SceneFile, SceneError> {
+    parse_scene_from_str_with_warnings(text, format).map(|(scene, _)| scene)
+}
+
+/// Parses a scene like `parse_scene_from_str`, also returning the changes
+/// made to upgrade it from an older version of the format.
+pub fn parse_scene_from_str_with_warnings(
+    text: &str,
+    format: SceneFormat,
+) -> Result<(SceneFile, Vec<MigrationWarning>), SceneError> {
+    match format {
+        SceneFormat::Toml => toml::parse_toml_scene_with_warnings(text),
+        SceneFormat::Json => json::parse_json_scene_with_warnings(text),
+        SceneFormat::Yaml => Ok((yaml::parse_yaml_scene_from_str(text)?, Vec::new())),
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::error::SceneError;
     use std::io::Write;
 
     #[test]
@@ -60,4 +130,83 @@ mod tests {
         let err = load_scene("unknown.toml");
         assert!(matches!(err, Err(SceneError::InvalidFile(_))));
     }
+
+    const JSON: &str = r#"{
+        "camera": { "position": [0, 0, 0], "target": [0, 0, -1], "fov": 40.0 },
+        "ambient": { "intensity": 0.4, "color": [255, 255, 255] },
+        "lights": [
+            { "type": "point", "position": [-10, 10, -10], "intensity": 0.5, "color": [255, 255, 255] }
+        ],
+        "objects": [
+            { "type": "sphere", "position": [0, 0, -30], "radius": 5.0, "material": { "type": "default" }, "color": [136, 8, 8] }
+        ]
+    }"#;
+
+    const YAML: &str = r#"
+# a book scene
+- add: camera
+  field-of-view: 0.785
+  from: [0, 0, 0]
+  to: [0, 0, -1]
+- add: light
+  at: [-10, 10, -10]
+  intensity: [1, 1, 1]
+- add: sphere
+  transform:
+    - [translate, 0, 0, -30]
+"#;
+
+    #[test]
+    fn load_scene_by_format_ok() {
+        let dir = tempfile::tempdir().expect("create temp dir");
+        for (name, text) in [
+            ("scene.json", JSON),
+            ("scene.yml", YAML),
+            ("json-scene", JSON),
+            ("yaml-scene", YAML),
+        ] {
+            let path = dir.path().join(name);
+            fs::write(&path, text).expect("write scene");
+            let scene = load_scene(&path).unwrap();
+            assert_eq!(scene.camera.target, [0.0, 0.0, -1.0]);
+            assert_eq!(scene.objects.len(), 1);
+        }
+    }
+
+    #[test]
+    fn load_scene_with_warnings_ok() {
+        let dir = tempfile::tempdir().expect("create temp dir");
+        let path = dir.path().join("scene.json");
+        fs::write(&path, JSON).expect("write scene");
+        let (scene, warnings) = load_scene_with_warnings(&path).unwrap();
+        assert_eq!(scene.version, scene_types::CURRENT_VERSION);
+        let paths: Vec<&str> = warnings.iter().map(|w| w.path.as_str()).collect();
+        assert_eq!(paths, ["version", "lights[0]", "objects[0].material"]);
+
+        let current = writer::scene_to_json_string(&scene).unwrap();
+        let (_, warnings) =
+            parse_scene_from_str_with_warnings(&current, SceneFormat::Json).unwrap();
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn load_scene_from_reader_ok() {
+        let scene = load_scene_from_reader(JSON.as_bytes(), None).unwrap();
+        assert_eq!(scene.lights[0].intensity, [0.5, 0.5, 0.5]);
+
+        let scene = load_scene_from_reader(YAML.as_bytes(), None).unwrap();
+        assert_eq!(scene.lights[0].intensity, [1.0, 1.0, 1.0]);
+
+        let scene = load_scene_from_reader(JSON.as_bytes(), Some(SceneFormat::Json)).unwrap();
+        assert_eq!(scene.objects.len(), 1);
+    }
+
+    #[test]
+    fn load_scene_from_reader_yields_error() {
+        let err = load_scene_from_reader(JSON.as_bytes(), Some(SceneFormat::Toml));
+        assert!(matches!(err, Err(SceneError::InvalidScene(_))));
+
+        let err = load_scene_from_reader(YAML.as_bytes(), Some(SceneFormat::Json));
+        assert!(matches!(err, Err(SceneError::InvalidJson(_))));
+    }
 }
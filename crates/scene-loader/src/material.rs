@@ -0,0 +1,166 @@
+use crate::error::SceneError;
+use ray_tracer::shape::Material;
+use scene_types::{MaterialCustomDef, NamedMaterialDef};
+use std::collections::BTreeMap;
+
+/// Resolves an entry of the `[materials]` table into a `Material`.
+///
+/// The `extend` chain is walked up to a material without a parent, which
+/// starts from the default material; each material then overrides the
+/// fields it sets, the requested one last. Colors are set per object and
+/// are left white.
+pub fn resolve_material(
+    name: &str,
+    materials: &BTreeMap<String, NamedMaterialDef>,
+) -> Result<Material, SceneError> {
+    let chain = material_chain(name, materials)?;
+    Ok(material_from(&resolve_chain(&chain)))
+}
+
+/// Resolves an inline material like `resolve_material`, from its own
+/// fields and those of the `[materials]` entry it extends.
+pub fn resolve_inline_material(
+    def: &NamedMaterialDef,
+    materials: &BTreeMap<String, NamedMaterialDef>,
+) -> Result<Material, SceneError> {
+    let mut chain = vec![def];
+    if let Some(parent) = &def.extend {
+        chain.extend(material_chain(parent, materials)?);
+    }
+    Ok(material_from(&resolve_chain(&chain)))
+}
+
+/// Applies a material and its ancestors, nearest first, over the default
+/// material.
+fn resolve_chain(chain: &[&NamedMaterialDef]) -> MaterialCustomDef {
+    let mut res = MaterialCustomDef::default();
+    for def in chain.iter().rev() {
+        res.ambient_coeff = def.ambient_coeff.unwrap_or(res.ambient_coeff);
+        res.diffuse_coeff = def.diffuse_coeff.unwrap_or(res.diffuse_coeff);
+        res.specular_coeff = def.specular_coeff.unwrap_or(res.specular_coeff);
+        res.shininess = def.shininess.unwrap_or(res.shininess);
+    }
+    res
+}
+
+/// Converts Phong coefficients into a white `Material`.
+pub fn material_from(def: &MaterialCustomDef) -> Material {
+    let mut res = Material::new();
+    res.ambient = def.ambient_coeff as f64;
+    res.diffuse = def.diffuse_coeff as f64;
+    res.specular = def.specular_coeff as f64;
+    res.shininess = def.shininess as f64;
+    res
+}
+
+/// Returns the material called `name` followed by its ancestors.
+///
+/// Fails with `UnknownMaterial` if a name in the chain is not defined and
+/// with `MaterialCycle` if the chain loops back on itself.
+pub fn material_chain<'a>(
+    name: &'a str,
+    materials: &'a BTreeMap<String, NamedMaterialDef>,
+) -> Result<Vec<&'a NamedMaterialDef>, SceneError> {
+    let mut names: Vec<&str> = Vec::new();
+    let mut chain: Vec<&NamedMaterialDef> = Vec::new();
+    let mut current = name;
+
+    loop {
+        if let Some(start) = names.iter().position(|n| *n == current) {
+            let mut cycle: Vec<String> = names[start..].iter().map(|n| n.to_string()).collect();
+            cycle.push(current.to_string());
+            return Err(SceneError::MaterialCycle(cycle));
+        }
+        let def = materials
+            .get(current)
+            .ok_or_else(|| SceneError::UnknownMaterial(current.to_string()))?;
+        names.push(current);
+        chain.push(def);
+        match &def.extend {
+            Some(parent) => current = parent,
+            None => return Ok(chain),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn library(entries: &[(&str, NamedMaterialDef)]) -> BTreeMap<String, NamedMaterialDef> {
+        entries
+            .iter()
+            .map(|(name, def)| (name.to_string(), def.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn resolve_material_ok() {
+        let materials = library(&[
+            (
+                "matte",
+                NamedMaterialDef {
+                    specular_coeff: Some(0.0),
+                    shininess: Some(10.0),
+                    ..Default::default()
+                },
+            ),
+            (
+                "dark-matte",
+                NamedMaterialDef {
+                    extend: Some("matte".to_string()),
+                    diffuse_coeff: Some(0.25),
+                    shininess: Some(5.0),
+                    ..Default::default()
+                },
+            ),
+        ]);
+
+        let res = resolve_material("matte", &materials).unwrap();
+        let mut expected = material_from(&MaterialCustomDef::default());
+        expected.specular = 0.0;
+        expected.shininess = 10.0;
+        assert_eq!(res, expected);
+
+        let res = resolve_material("dark-matte", &materials).unwrap();
+        expected.diffuse = 0.25;
+        expected.shininess = 5.0;
+        assert_eq!(res, expected);
+
+        let inline = NamedMaterialDef {
+            extend: Some("dark-matte".to_string()),
+            ambient_coeff: Some(0.5),
+            ..Default::default()
+        };
+        let res = resolve_inline_material(&inline, &materials).unwrap();
+        expected.ambient = 0.5;
+        assert_eq!(res, expected);
+    }
+
+    #[test]
+    fn resolve_material_yields_error() {
+        let extend = |parent: &str| NamedMaterialDef {
+            extend: Some(parent.to_string()),
+            ..Default::default()
+        };
+        let materials = library(&[
+            ("a", extend("b")),
+            ("b", extend("c")),
+            ("c", extend("b")),
+            ("d", extend("missing")),
+        ]);
+
+        let err = resolve_material("unknown", &materials);
+        assert!(matches!(err, Err(SceneError::UnknownMaterial(ref n)) if n == "unknown"));
+
+        let err = resolve_material("d", &materials);
+        assert!(matches!(err, Err(SceneError::UnknownMaterial(ref n)) if n == "missing"));
+
+        let err = resolve_inline_material(&extend("d"), &materials);
+        assert!(matches!(err, Err(SceneError::UnknownMaterial(ref n)) if n == "missing"));
+
+        let err = resolve_material("a", &materials).unwrap_err();
+        assert!(matches!(err, SceneError::MaterialCycle(ref c) if c == &["b", "c", "b"]));
+        assert_eq!(err.to_string(), "material inheritance cycle: b -> c -> b");
+    }
+}
@@ -0,0 +1,284 @@
+use crate::error::SceneError;
+use crate::expr::evaluate_scene_expressions;
+use scene_types::CURRENT_VERSION;
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value};
+use std::fmt;
+use std::path::PathBuf;
+
+/// A change made while upgrading a scene written for an older version of
+/// the format.
+///
+/// ## Fields
+/// - `path`: The upgraded value, e.g. `lights[0]`.
+/// - `message`: What was changed.
+/// - `file`: The file the value is written in, when loaded from disk.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MigrationWarning {
+    pub path: String,
+    pub message: String,
+    pub file: Option<PathBuf>,
+}
+
+impl fmt::Display for MigrationWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.file {
+            Some(file) => write!(f, "{} ({}): {}", self.path, file.display(), self.message),
+            None => write!(f, "{}: {}", self.path, self.message),
+        }
+    }
+}
+
+/// Upgrades a scene by one version, from the version at the same index
+/// plus one.
+type Migration = fn(&mut Map<String, Value>, &mut Vec<MigrationWarning>);
+
+const MIGRATIONS: [Migration; (CURRENT_VERSION - 1) as usize] = [upgrade_to_v2];
+
+/// The `version` of a scene, read before the rest of it.
+#[derive(Deserialize)]
+struct Version {
+    version: Option<u32>,
+}
+
+/// Parses a TOML scene, or a part of one, upgrading it first when it was
+/// written for an older version of the format, then evaluating its
+/// `[vars]` and expressions.
+///
+/// Scenes of the current version without expressions are parsed directly,
+/// so that their errors keep pointing at the offending line.
+pub(crate) fn from_toml_str<T: DeserializeOwned>(
+    text: &str,
+) -> Result<(T, Vec<MigrationWarning>), SceneError> {
+    let version = toml::from_str::<Version>(text)?.version;
+    let mut value = serde_json::to_value(toml::from_str::<toml::Table>(text)?)?;
+    let warnings = migrate(&mut value, version)?;
+    if !evaluate_scene_expressions(&mut value)? && warnings.is_empty() {
+        return Ok((toml::from_str(text)?, warnings));
+    }
+    let value =
+        toml::Value::try_from(value).map_err(|err| SceneError::Serialize(err.to_string()))?;
+    Ok((value.try_into()?, warnings))
+}
+
+/// Parses a JSON scene like `from_toml_str`.
+pub(crate) fn from_json_str<T: DeserializeOwned>(
+    text: &str,
+) -> Result<(T, Vec<MigrationWarning>), SceneError> {
+    let version = serde_json::from_str::<Version>(text)?.version;
+    let mut value: Value = serde_json::from_str(text)?;
+    let warnings = migrate(&mut value, version)?;
+    if !evaluate_scene_expressions(&mut value)? && warnings.is_empty() {
+        return Ok((serde_json::from_str(text)?, warnings));
+    }
+    Ok((serde_json::from_value(value)?, warnings))
+}
+
+/// Upgrades a scene written for `version`, or for version 1 when it has
+/// none, to `CURRENT_VERSION`, and returns what was changed. Scenes of the
+/// current version are left unchanged.
+///
+/// Values that do not have the expected older shape are left alone, so
+/// that deserializing the upgraded scene reports them.
+pub(crate) fn migrate(
+    value: &mut Value,
+    version: Option<u32>,
+) -> Result<Vec<MigrationWarning>, SceneError> {
+    if version == Some(CURRENT_VERSION) {
+        return Ok(Vec::new());
+    }
+    let from = version.unwrap_or(1);
+    if from == 0 || from > CURRENT_VERSION {
+        return Err(SceneError::UnsupportedVersion(from));
+    }
+    let Some(scene) = value.as_object_mut() else {
+        return Ok(Vec::new());
+    };
+
+    let message = match version {
+        Some(_) => format!("upgraded from version {from} to {CURRENT_VERSION}"),
+        None => format!("missing, upgraded from version 1 to {CURRENT_VERSION}"),
+    };
+    let mut warnings = vec![warning("version".to_string(), message)];
+    for migration in &MIGRATIONS[(from - 1) as usize..] {
+        migration(scene, &mut warnings);
+    }
+    scene.insert("version".to_string(), CURRENT_VERSION.into());
+    Ok(warnings)
+}
+
+/// Version 2 merged the scalar `intensity` and the 8-bit `color` of lights
+/// into an RGB `intensity`, and dropped the `type` tag of inline materials.
+fn upgrade_to_v2(scene: &mut Map<String, Value>, warnings: &mut Vec<MigrationWarning>) {
+    if let Some(Value::Array(lights)) = scene.get_mut("lights") {
+        for (i, light) in lights.iter_mut().enumerate() {
+            let Some(light) = light.as_object_mut() else {
+                continue;
+            };
+            let Some(intensity) = light.get("intensity").and_then(Value::as_f64) else {
+                continue;
+            };
+            let color = match light.get("color") {
+                None => [255.0; 3],
+                Some(color) => match as_rgb(color) {
+                    Some(color) => color,
+                    None => continue,
+                },
+            };
+            light.remove("color");
+            let rgb: Vec<Value> = color
+                .iter()
+                .map(|c| (intensity * c / 255.0).into())
+                .collect();
+            light.insert("intensity".to_string(), rgb.into());
+            warnings.push(warning(
+                format!("lights[{i}]"),
+                "merged the scalar `intensity` and `color` into an RGB `intensity`".to_string(),
+            ));
+        }
+    }
+
+    if let Some(Value::Array(objects)) = scene.get_mut("objects") {
+        for (i, object) in objects.iter_mut().enumerate() {
+            let Some(object) = object.as_object_mut() else {
+                continue;
+            };
+            let Some(Value::Object(material)) = object.get_mut("material") else {
+                continue;
+            };
+            let message = match material.get("type").and_then(Value::as_str) {
+                Some("default") if material.len() == 1 => {
+                    object.remove("material");
+                    "removed `type = \"default\"`, the default material is implied"
+                }
+                Some("custom") => {
+                    material.remove("type");
+                    "removed `type = \"custom\"`, inline materials are untagged"
+                }
+                _ => continue,
+            };
+            warnings.push(warning(
+                format!("objects[{i}].material"),
+                message.to_string(),
+            ));
+        }
+    }
+}
+
+fn as_rgb(value: &Value) -> Option<[f64; 3]> {
+    match value.as_array()?.as_slice() {
+        [r, g, b] => Some([r.as_f64()?, g.as_f64()?, b.as_f64()?]),
+        _ => None,
+    }
+}
+
+fn warning(path: String, message: String) -> MigrationWarning {
+    MigrationWarning {
+        path,
+        message,
+        file: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use scene_types::{MaterialDef, NamedMaterialDef, SceneFile};
+
+    const SCENE_V1: &str = r#"
+        [ambient]
+        intensity = 0.5
+        color = [255, 255, 255]
+
+        [[lights]]
+        type = "point"
+        position = [-10, 10, -10]
+        intensity = 0.5
+        color = [255, 0, 51]
+
+        [[objects]]
+        type = "sphere"
+        position = [0, 0, 0]
+        radius = 1.0
+        material = { type = "default" }
+
+        [[objects]]
+        type = "sphere"
+        position = [2, 0, 0]
+        radius = 1.0
+        material = { type = "custom", shininess = 50.0 }
+    "#;
+
+    #[test]
+    fn migrate_toml_ok() {
+        let (scene, warnings) = from_toml_str::<SceneFile>(SCENE_V1).unwrap();
+        assert_eq!(scene.version, CURRENT_VERSION);
+        assert_eq!(scene.lights[0].intensity, [0.5, 0.0, 0.1]);
+        assert_eq!(scene.objects[0].material, MaterialDef::default());
+        assert_eq!(
+            scene.objects[1].material,
+            MaterialDef::Inline(NamedMaterialDef {
+                shininess: Some(50.0),
+                ..Default::default()
+            })
+        );
+
+        let paths: Vec<&str> = warnings.iter().map(|w| w.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            [
+                "version",
+                "lights[0]",
+                "objects[0].material",
+                "objects[1].material"
+            ]
+        );
+        assert_eq!(
+            warnings[0].to_string(),
+            "version: missing, upgraded from version 1 to 2"
+        );
+
+        let current = toml::to_string(&scene).unwrap();
+        let (res, warnings) = from_toml_str::<SceneFile>(&current).unwrap();
+        assert_eq!(res, scene);
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn migrate_json_ok() {
+        let json = r#"{
+            "version": 1,
+            "lights": [
+                { "type": "point", "position": [0, 0, 0], "intensity": 2.0 }
+            ],
+            "objects": [
+                { "type": "sphere", "position": [0, 0, 0], "radius": 1.0 }
+            ]
+        }"#;
+        let (scene, warnings) = from_json_str::<SceneFile>(json).unwrap();
+        assert_eq!(scene.lights[0].intensity, [2.0, 2.0, 2.0]);
+        assert_eq!(
+            warnings[0].to_string(),
+            "version: upgraded from version 1 to 2"
+        );
+        assert_eq!(warnings.len(), 2);
+    }
+
+    #[test]
+    fn migrate_yields_error() {
+        let err = from_toml_str::<SceneFile>("version = 3\n");
+        assert!(matches!(err, Err(SceneError::UnsupportedVersion(3))));
+
+        let err = from_json_str::<SceneFile>(r#"{ "version": 0 }"#);
+        assert!(matches!(err, Err(SceneError::UnsupportedVersion(0))));
+
+        let err = from_toml_str::<SceneFile>(r#"version = "2""#);
+        assert!(matches!(err, Err(SceneError::InvalidScene(_))));
+
+        // an unknown material tag is left for deserialization to reject
+        let source = SCENE_V1.replace("\"custom\"", "\"UNKNOWN\"");
+        let err = from_toml_str::<SceneFile>(&source);
+        assert!(matches!(err, Err(SceneError::InvalidScene(_))));
+    }
+}
@@ -1,23 +1,39 @@
 use crate::error::SceneError;
+use crate::migrate::{MigrationWarning, from_toml_str};
+use crate::validate::validate_scene_source;
 use scene_types::SceneFile;
 
+/// Parses and validates a TOML scene. Validation errors point at the line
+/// and column of the offending value.
+///
+/// Scenes written for an older version of the format are upgraded first.
 pub fn parse_toml_scene_from_str(toml_str: &str) -> Result<SceneFile, SceneError> {
-    let scene: SceneFile = toml::from_str(toml_str)?;
-    Ok(scene)
+    parse_toml_scene_with_warnings(toml_str).map(|(scene, _)| scene)
+}
+
+pub(crate) fn parse_toml_scene_with_warnings(
+    toml_str: &str,
+) -> Result<(SceneFile, Vec<MigrationWarning>), SceneError> {
+    let (scene, warnings) = from_toml_str::<SceneFile>(toml_str)?;
+    validate_scene_source(&scene, toml_str)?;
+    Ok((scene, warnings))
 }
 
 #[cfg(test)]
 mod tests {
     use scene_types::{
-        AmbientDef, CameraDef, LightDef, LightKindDef, MaterialCustomDef, MaterialDef,
-        MaterialEmptyDef, ObjectDef, ShapeDef,
+        AmbientDef, CURRENT_VERSION, CameraDef, LightDef, LightKindDef, MaterialDef,
+        NamedMaterialDef, ObjectDef, ShapeDef,
     };
 
     use super::*;
+    use std::collections::BTreeMap;
 
     #[test]
     fn parse_toml_scene_from_str_ok() {
         let input = r#"
+            version = 2
+
             [camera]
             position = [0, 0, 0]
             target = [0, 0, -1]
@@ -30,15 +46,13 @@ mod tests {
             [[lights]]
             type = "point"
             position = [-10, 10, -10]
-            intensity = 0.5
-            color = [255, 255, 255]
+            intensity = [0.5, 0.5, 0.5]
 
             [[objects]]
             type = "sphere"
             position = [0, 0, -30]
             radius = 5.0
             material = {
-                    type = "custom",
                     ambient-coefficient = 0.1,
                     diffuse-coefficient = 0.9,
                     specular-coefficient = 0.9,
@@ -51,6 +65,7 @@ mod tests {
         let scene: SceneFile = parse_toml_scene_from_str(&input).unwrap();
 
         let mut scene_expected: SceneFile = SceneFile {
+            version: CURRENT_VERSION,
             camera: CameraDef {
                 position: [0_f32, 0_f32, 0_f32],
                 target: [0_f32, 0_f32, -1_f32],
@@ -60,27 +75,31 @@ mod tests {
                 intensity: 0.4,
                 color: [255, 255, 255],
             },
+            materials: BTreeMap::new(),
             lights: vec![LightDef {
                 kind: LightKindDef::Point,
                 position: [-10_f32, 10_f32, -10_f32],
-                intensity: 0.5,
-                color: [255, 255, 255],
+                intensity: [0.5, 0.5, 0.5],
             }],
             objects: vec![ObjectDef {
                 position: [0_f32, 0_f32, -30_f32],
-                material: MaterialDef::Custom(MaterialCustomDef {
-                    ambient_coeff: 0.1,
-                    diffuse_coeff: 0.9,
-                    specular_coeff: 0.9,
-                    shininess: 200.0,
+                material: MaterialDef::Inline(NamedMaterialDef {
+                    extend: None,
+                    ambient_coeff: Some(0.1),
+                    diffuse_coeff: Some(0.9),
+                    specular_coeff: Some(0.9),
+                    shininess: Some(200.0),
                 }),
                 color: [136, 8, 8],
+                transform: vec![],
                 shape: ShapeDef::Sphere { radius: 5.0 },
             }],
+            animation: None,
         };
 
         assert_eq!(scene, scene_expected);
 
+        // scenes without a version are upgraded
         let input = r#"
             [camera]
             position = [0, 0, 0]
@@ -107,11 +126,49 @@ mod tests {
         .to_string();
 
         let scene: SceneFile = parse_toml_scene_from_str(&input).unwrap();
-        scene_expected.objects[0].material = MaterialDef::Default(MaterialEmptyDef {});
+        scene_expected.objects[0].material = MaterialDef::default();
 
         assert_eq!(scene, scene_expected);
     }
 
+    #[test]
+    fn parse_toml_scene_from_str_defaults_ok() {
+        let input = r#"
+            version = 2
+
+            [[lights]]
+            type = "point"
+            position = [-10, 10, -10]
+            intensity = [1, 1, 1]
+
+            [[objects]]
+            type = "sphere"
+            position = [0, 0, 0]
+            radius = 1.0
+            material = { shininess = 50.0 }
+
+            [[objects]]
+            type = "sphere"
+            position = [2, 0, 0]
+            radius = 1.0
+        "#;
+
+        let scene = parse_toml_scene_from_str(input).unwrap();
+        assert_eq!(scene.camera, CameraDef::default());
+        assert_eq!(scene.ambient, AmbientDef::default());
+        assert_eq!(scene.ambient.intensity, 0.0);
+        assert_eq!(
+            scene.objects[0].material,
+            MaterialDef::Inline(NamedMaterialDef {
+                shininess: Some(50.0),
+                ..Default::default()
+            })
+        );
+        assert_eq!(scene.objects[0].color, scene_types::DEFAULT_COLOR);
+        assert_eq!(scene.objects[1].material, MaterialDef::default());
+        assert_eq!(scene.objects[1].color, [255, 255, 255]);
+    }
+
     #[test]
     fn parse_toml_scene_from_str_yields_error() {
         let input = r#"
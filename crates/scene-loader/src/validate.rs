@@ -0,0 +1,623 @@
+use crate::animation::check_target;
+use crate::builder::build_transform_step;
+use crate::error::SceneError;
+use crate::material::material_chain;
+use scene_types::{KeyValueDef, MaterialDef, SceneFile, ShapeDef};
+use std::fmt;
+use std::path::PathBuf;
+use toml::de::DeTable;
+
+/// A 1-based line and column in a scene source file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SourceLocation {
+    pub line: usize,
+    pub column: usize,
+}
+
+/// A semantic problem found in a scene that parsed successfully.
+///
+/// ## Fields
+/// - `path`: The offending value, e.g. `objects[3].radius`.
+/// - `message`: What is wrong with the value.
+/// - `file`: The file the value is written in, when loaded from disk.
+/// - `location`: Where the value is written, when the source is known.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValidationIssue {
+    pub path: String,
+    pub message: String,
+    pub file: Option<PathBuf>,
+    pub location: Option<SourceLocation>,
+}
+
+impl fmt::Display for ValidationIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let file = self.file.as_ref().map(|file| file.display());
+        match (file, self.location) {
+            (Some(file), Some(loc)) => write!(
+                f,
+                "{} ({}, line {}, column {}): {}",
+                self.path, file, loc.line, loc.column, self.message
+            ),
+            (Some(file), None) => write!(f, "{} ({}): {}", self.path, file, self.message),
+            (None, Some(loc)) => write!(
+                f,
+                "{} (line {}, column {}): {}",
+                self.path, loc.line, loc.column, self.message
+            ),
+            (None, None) => write!(f, "{}: {}", self.path, self.message),
+        }
+    }
+}
+
+/// Joins issues into the message of `SceneError::Validation`.
+pub(crate) fn describe_issues(issues: &[ValidationIssue]) -> String {
+    issues
+        .iter()
+        .map(ValidationIssue::to_string)
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+/// A step of the path to a value: a table key or an array index.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Segment<'a> {
+    Key(&'a str),
+    Index(usize),
+}
+
+struct Problem<'a> {
+    path: Vec<Segment<'a>>,
+    message: String,
+}
+
+/// Checks a scene for values that parse but cannot be rendered.
+///
+/// Every problem is reported, not only the first one. Issues carry no
+/// source location; use `validate_scene_source` when the text is at hand.
+pub fn validate_scene(scene: &SceneFile) -> Result<(), SceneError> {
+    validate_scene_with(scene, |_| (None, None))
+}
+
+/// Checks a scene like `validate_scene`, locating each issue in the TOML
+/// text the scene was parsed from.
+pub fn validate_scene_source(scene: &SceneFile, source: &str) -> Result<(), SceneError> {
+    validate_scene_with(scene, |path| (None, locate_in_source(source, path)))
+}
+
+/// Checks a scene, asking `locate` for the file and position of the value
+/// behind each issue.
+pub(crate) fn validate_scene_with<'a>(
+    scene: &'a SceneFile,
+    locate: impl Fn(&[Segment<'a>]) -> (Option<PathBuf>, Option<SourceLocation>),
+) -> Result<(), SceneError> {
+    let problems = find_problems(scene);
+    if problems.is_empty() {
+        return Ok(());
+    }
+    let issues = problems
+        .into_iter()
+        .map(|problem| {
+            let (file, location) = locate(&problem.path);
+            ValidationIssue {
+                path: format_path(&problem.path),
+                message: problem.message,
+                file,
+                location,
+            }
+        })
+        .collect();
+    Err(SceneError::Validation(issues))
+}
+
+/// Returns the line and column of the value at `path` in a TOML document.
+/// If the path does not exist, the deepest value that does exist is used.
+pub(crate) fn locate_in_source(source: &str, path: &[Segment<'_>]) -> Option<SourceLocation> {
+    let document = DeTable::parse(source).ok()?;
+    let offset = locate(document.get_ref(), path)?;
+    Some(location_of(source, offset))
+}
+
+fn find_problems<'a>(scene: &'a SceneFile) -> Vec<Problem<'a>> {
+    let mut problems: Vec<Problem<'a>> = Vec::new();
+    let mut report =
+        |path: Vec<Segment<'a>>, message: String| problems.push(Problem { path, message });
+
+    let camera = &scene.camera;
+    // every check is false for NaN, so NaN values are rejected too
+    let fov_ok = camera.fov > 0.0 && camera.fov < 180.0;
+    if !fov_ok {
+        report(
+            vec![Segment::Key("camera"), Segment::Key("fov")],
+            format!("must be between 0 and 180 degrees, got {}", camera.fov),
+        );
+    }
+    if camera.target == camera.position {
+        report(
+            vec![Segment::Key("camera"), Segment::Key("target")],
+            "must differ from the camera position".to_string(),
+        );
+    }
+
+    if !is_non_negative(scene.ambient.intensity) {
+        report(
+            vec![Segment::Key("ambient"), Segment::Key("intensity")],
+            format!("must not be negative, got {}", scene.ambient.intensity),
+        );
+    }
+
+    for (i, light) in scene.lights.iter().enumerate() {
+        if !light.intensity.iter().all(|c| is_non_negative(*c)) {
+            report(
+                vec![
+                    Segment::Key("lights"),
+                    Segment::Index(i),
+                    Segment::Key("intensity"),
+                ],
+                format!("must not be negative, got {:?}", light.intensity),
+            );
+        }
+    }
+
+    for (name, def) in &scene.materials {
+        let at = |key: &'static str| {
+            vec![
+                Segment::Key("materials"),
+                Segment::Key(name.as_str()),
+                Segment::Key(key),
+            ]
+        };
+        match material_chain(name, &scene.materials) {
+            Err(SceneError::MaterialCycle(cycle)) => {
+                // report a cycle once, on its first member in name order
+                let first = cycle.iter().min();
+                if cycle[0] == *name && first == Some(name) {
+                    report(at("extend"), SceneError::MaterialCycle(cycle).to_string());
+                }
+            }
+            // a broken ancestor is reported where it is extended
+            Err(err @ SceneError::UnknownMaterial(_))
+                if def
+                    .extend
+                    .as_ref()
+                    .is_some_and(|p| !scene.materials.contains_key(p)) =>
+            {
+                report(at("extend"), err.to_string());
+            }
+            _ => {}
+        }
+        for (key, value) in [
+            ("ambient-coefficient", def.ambient_coeff),
+            ("diffuse-coefficient", def.diffuse_coeff),
+            ("specular-coefficient", def.specular_coeff),
+            ("shininess", def.shininess),
+        ] {
+            if let Some(value) = value.filter(|v| !is_non_negative(*v)) {
+                report(at(key), format!("must not be negative, got {value}"));
+            }
+        }
+    }
+
+    if scene.objects.is_empty() {
+        report(
+            vec![Segment::Key("objects")],
+            "a scene needs at least one object".to_string(),
+        );
+    }
+    for (i, object) in scene.objects.iter().enumerate() {
+        let at = |key: &'static str| {
+            vec![
+                Segment::Key("objects"),
+                Segment::Index(i),
+                Segment::Key(key),
+            ]
+        };
+        match object.shape {
+            ShapeDef::Sphere { radius } => {
+                if !is_positive(radius) {
+                    report(at("radius"), format!("must be positive, got {radius}"));
+                }
+            }
+            ShapeDef::Plane => {}
+        }
+        for (j, step) in object.transform.iter().enumerate() {
+            if !build_transform_step(step).is_invertible() {
+                let mut path = at("transform");
+                path.push(Segment::Index(j));
+                report(path, "must be invertible".to_string());
+            }
+        }
+        match &object.material {
+            MaterialDef::Inline(m) => {
+                let at_material = |key: &'static str| {
+                    let mut path = at("material");
+                    path.push(Segment::Key(key));
+                    path
+                };
+                if let Some(parent) = m.extend.as_ref()
+                    && !scene.materials.contains_key(parent)
+                {
+                    report(
+                        at_material("extend"),
+                        SceneError::UnknownMaterial(parent.clone()).to_string(),
+                    );
+                }
+                for (key, value) in [
+                    ("ambient-coefficient", m.ambient_coeff),
+                    ("diffuse-coefficient", m.diffuse_coeff),
+                    ("specular-coefficient", m.specular_coeff),
+                    ("shininess", m.shininess),
+                ] {
+                    if let Some(value) = value.filter(|v| !is_non_negative(*v)) {
+                        report(
+                            at_material(key),
+                            format!("must not be negative, got {value}"),
+                        );
+                    }
+                }
+            }
+            MaterialDef::Named(name) if !scene.materials.contains_key(name) => {
+                report(
+                    at("material"),
+                    SceneError::UnknownMaterial(name.clone()).to_string(),
+                );
+            }
+            _ => {}
+        }
+    }
+
+    if let Some(animation) = &scene.animation {
+        let at = |keys: &[Segment<'a>]| {
+            let mut path = vec![Segment::Key("animation")];
+            path.extend_from_slice(keys);
+            path
+        };
+        if animation.frames == 0 {
+            report(
+                at(&[Segment::Key("frames")]),
+                "must be at least 1".to_string(),
+            );
+        }
+        if !is_positive(animation.fps) {
+            report(
+                at(&[Segment::Key("fps")]),
+                format!("must be positive, got {}", animation.fps),
+            );
+        }
+        // targets are checked against the scene as it is written
+        let written = match animation.tracks.is_empty() {
+            true => None,
+            false => serde_json::to_value(scene).ok(),
+        };
+        for (i, track) in animation.tracks.iter().enumerate() {
+            let track_at = |key: &'static str| {
+                at(&[Segment::Key("tracks"), Segment::Index(i), Segment::Key(key)])
+            };
+            let Some(first) = track.keyframes.first() else {
+                report(
+                    track_at("keyframes"),
+                    "a track needs at least one keyframe".to_string(),
+                );
+                continue;
+            };
+            if let Some(written) = &written
+                && let Err(message) = check_target(written, &track.target, &first.value)
+            {
+                report(track_at("target"), message);
+            }
+            let mut previous: Option<u32> = None;
+            for (j, key) in track.keyframes.iter().enumerate() {
+                let key_at = |key: &'static str| {
+                    let mut path = track_at("keyframes");
+                    path.extend([Segment::Index(j), Segment::Key(key)]);
+                    path
+                };
+                if key.frame == 0 || key.frame > animation.frames {
+                    report(
+                        key_at("frame"),
+                        format!(
+                            "must be between 1 and {}, got {}",
+                            animation.frames, key.frame
+                        ),
+                    );
+                } else if let Some(previous) = previous.filter(|p| key.frame <= *p) {
+                    report(
+                        key_at("frame"),
+                        format!("must come after frame {previous}, got {}", key.frame),
+                    );
+                }
+                previous = Some(key.frame);
+                if !same_shape(&key.value, &first.value) {
+                    report(
+                        key_at("value"),
+                        "must have the shape of the first keyframe".to_string(),
+                    );
+                }
+            }
+        }
+    }
+
+    problems
+}
+
+fn same_shape(a: &KeyValueDef, b: &KeyValueDef) -> bool {
+    match (a, b) {
+        (KeyValueDef::Scalar(_), KeyValueDef::Scalar(_)) => true,
+        (KeyValueDef::Vector(a), KeyValueDef::Vector(b)) => a.len() == b.len(),
+        _ => false,
+    }
+}
+
+fn is_positive(value: f32) -> bool {
+    value > 0.0
+}
+
+fn is_non_negative(value: f32) -> bool {
+    value >= 0.0
+}
+
+fn format_path(path: &[Segment<'_>]) -> String {
+    let mut res = String::new();
+    for segment in path {
+        match segment {
+            Segment::Key(key) => {
+                if !res.is_empty() {
+                    res.push('.');
+                }
+                res.push_str(key);
+            }
+            Segment::Index(i) => res.push_str(&format!("[{i}]")),
+        }
+    }
+    res
+}
+
+fn locate(document: &DeTable<'_>, path: &[Segment<'_>]) -> Option<usize> {
+    let (Segment::Key(first), rest) = path.split_first()? else {
+        return None;
+    };
+    let mut value = document.get(*first)?;
+    for segment in rest {
+        let next = match segment {
+            Segment::Key(key) => value.get_ref().get(*key),
+            Segment::Index(i) => value.get_ref().get(*i),
+        };
+        match next {
+            Some(next) => value = next,
+            None => break,
+        }
+    }
+    Some(value.span().start)
+}
+
+fn location_of(source: &str, offset: usize) -> SourceLocation {
+    let before = source.get(..offset).unwrap_or(source);
+    let line = before.matches('\n').count() + 1;
+    let column = before.rsplit('\n').next().unwrap_or("").chars().count() + 1;
+    SourceLocation { line, column }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::toml::parse_toml_scene_from_str;
+
+    const SCENE: &str = r#"version = 2
+[camera]
+position = [0, 0, -5]
+target = [0, 0, 0]
+fov = 60.0
+
+[ambient]
+intensity = 0.5
+color = [255, 255, 255]
+
+[[lights]]
+type = "point"
+position = [-10, 10, -10]
+intensity = [1, 1, 1]
+
+[[objects]]
+type = "sphere"
+position = [0, 0, 0]
+radius = 1.0
+material = { shininess = 200.0 }
+color = [255, 0, 0]
+
+[[objects]]
+type = "sphere"
+position = [0, 0, 3]
+radius = 2.0
+material = { ambient-coefficient = 0.1, diffuse-coefficient = 0.9, specular-coefficient = 0.9, shininess = 200.0 }
+color = [0, 255, 0]
+"#;
+
+    fn issues(source: &str) -> Vec<ValidationIssue> {
+        match parse_toml_scene_from_str(source) {
+            Err(SceneError::Validation(issues)) => issues,
+            other => panic!("expected a validation error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn validate_scene_ok() {
+        let scene = parse_toml_scene_from_str(SCENE).unwrap();
+        assert!(validate_scene(&scene).is_ok());
+        assert!(validate_scene_source(&scene, SCENE).is_ok());
+    }
+
+    #[test]
+    fn validate_scene_yields_error() {
+        let source = SCENE
+            .replace("radius = 2.0", "radius = -2.0")
+            .replace("fov = 60.0", "fov = 180.0")
+            .replace("diffuse-coefficient = 0.9", "diffuse-coefficient = -0.9");
+        let res = issues(&source);
+        assert_eq!(res.len(), 3);
+
+        assert_eq!(res[0].path, "camera.fov");
+        assert_eq!(res[0].location, Some(SourceLocation { line: 5, column: 7 }));
+        assert_eq!(res[1].path, "objects[1].radius");
+        assert_eq!(
+            res[1].location,
+            Some(SourceLocation {
+                line: 26,
+                column: 10
+            })
+        );
+        assert_eq!(res[2].path, "objects[1].material.diffuse-coefficient");
+        assert_eq!(res[2].location.map(|loc| loc.line), Some(27));
+        assert_eq!(
+            res[2].to_string(),
+            format!(
+                "objects[1].material.diffuse-coefficient (line 27, column {}): must not be negative, got -0.9",
+                res[2].location.unwrap().column
+            )
+        );
+    }
+
+    #[test]
+    fn validate_camera_yields_error() {
+        let source = SCENE.replace("target = [0, 0, 0]", "target = [0, 0, -5]");
+        let res = issues(&source);
+        assert_eq!(res.len(), 1);
+        assert_eq!(res[0].path, "camera.target");
+        assert_eq!(
+            res[0].location,
+            Some(SourceLocation {
+                line: 4,
+                column: 10
+            })
+        );
+
+        let mut scene = parse_toml_scene_from_str(SCENE).unwrap();
+        scene.camera.fov = f32::NAN;
+        let err = validate_scene(&scene);
+        assert!(matches!(err, Err(SceneError::Validation(ref v)) if v[0].location.is_none()));
+    }
+
+    #[test]
+    fn validate_materials_yields_error() {
+        let source = SCENE
+            .replace(
+                "[[lights]]",
+                r#"[materials.a]
+extend = "b"
+
+[materials.b]
+extend = "a"
+shininess = -1.0
+
+[materials.c]
+extend = "unknown"
+
+[[lights]]"#,
+            )
+            .replace("material = { shininess = 200.0 }", r#"material = "d""#);
+        let res = issues(&source);
+        assert_eq!(res.len(), 4);
+
+        assert_eq!(res[0].path, "materials.a.extend");
+        assert_eq!(res[0].message, "material inheritance cycle: a -> b -> a");
+        assert_eq!(
+            res[0].location,
+            Some(SourceLocation {
+                line: 12,
+                column: 10
+            })
+        );
+        assert_eq!(res[1].path, "materials.b.shininess");
+        assert_eq!(res[2].path, "materials.c.extend");
+        assert_eq!(res[2].message, "unknown material `unknown`");
+        assert_eq!(res[3].path, "objects[0].material");
+        assert_eq!(res[3].message, "unknown material `d`");
+        assert_eq!(res[3].location.map(|loc| loc.line), Some(30));
+    }
+
+    #[test]
+    fn validate_objects_yields_error() {
+        let mut scene = parse_toml_scene_from_str(SCENE).unwrap();
+        scene.objects.clear();
+        let Err(SceneError::Validation(res)) = validate_scene(&scene) else {
+            panic!("expected a validation error");
+        };
+        assert_eq!(res[0].path, "objects");
+        assert_eq!(
+            res[0].to_string(),
+            "objects: a scene needs at least one object"
+        );
+
+        let source = SCENE.replace("radius = 1.0", "radius = 0.0");
+        let res = issues(&source);
+        assert_eq!(res[0].path, "objects[0].radius");
+        assert_eq!(res[0].location.map(|loc| loc.line), Some(19));
+
+        let source = SCENE.replace(
+            "color = [255, 0, 0]",
+            "color = [255, 0, 0]\ntransform = [{ rotate_x = 1.0 }, { scale = [1, 0, 1] }]",
+        );
+        let res = issues(&source);
+        assert_eq!(res.len(), 1);
+        assert_eq!(res[0].path, "objects[0].transform[1]");
+        assert_eq!(
+            res[0].location,
+            Some(SourceLocation {
+                line: 22,
+                column: 34
+            })
+        );
+    }
+
+    #[test]
+    fn validate_animation_yields_error() {
+        let source = format!(
+            "{SCENE}{}",
+            r#"
+[animation]
+frames = 10
+fps = 0
+
+[[animation.tracks]]
+target = "objects[2].radius"
+keyframes = [{ frame = 1, value = 1 }]
+
+[[animation.tracks]]
+target = "camera.position"
+keyframes = [{ frame = 4, value = [0, 0, 1] }, { frame = 2, value = [1, 1] }, { frame = 11, value = [0, 0, 0] }]
+
+[[animation.tracks]]
+target = "camera.fvo"
+keyframes = [{ frame = 1, value = 1 }]
+
+[[animation.tracks]]
+target = "camera.fov"
+keyframes = []
+"#
+        );
+        let res = issues(&source);
+        let paths: Vec<&str> = res.iter().map(|issue| issue.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            [
+                "animation.fps",
+                "animation.tracks[0].target",
+                "animation.tracks[1].keyframes[1].frame",
+                "animation.tracks[1].keyframes[1].value",
+                "animation.tracks[1].keyframes[2].frame",
+                "animation.tracks[2].target",
+                "animation.tracks[3].keyframes",
+            ]
+        );
+        assert_eq!(
+            res[1].message,
+            "`objects[2].radius` does not name a value of the scene"
+        );
+        assert_eq!(res[1].location.map(|loc| loc.line), Some(35));
+        assert_eq!(res[2].message, "must come after frame 4, got 2");
+        assert_eq!(res[4].message, "must be between 1 and 10, got 11");
+        assert!(
+            res[5]
+                .message
+                .starts_with("`camera.fvo` cannot be animated: unknown field `fvo`")
+        );
+    }
+}
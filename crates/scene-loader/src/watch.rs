@@ -0,0 +1,219 @@
+use crate::error::SceneError;
+use crate::format::SceneFormat;
+use crate::load_scene_with_warnings;
+use crate::migrate::MigrationWarning;
+use scene_types::SceneFile;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+/// Returns the files a scene is read from: the scene file itself followed by
+/// every file it includes, directly or through other includes.
+///
+/// Files that cannot be read or parsed are still listed, without following
+/// their includes, so that a broken or missing include can be watched until
+/// it is fixed.
+pub fn scene_files<P: AsRef<Path>>(path: P) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    collect_files(path.as_ref(), &mut Vec::new(), &mut files);
+    files
+}
+
+fn collect_files(path: &Path, seen: &mut Vec<PathBuf>, files: &mut Vec<PathBuf>) {
+    let canonical = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    if seen.contains(&canonical) {
+        return;
+    }
+    seen.push(canonical);
+    files.push(path.to_path_buf());
+
+    let Ok(text) = fs::read_to_string(path) else {
+        return;
+    };
+    let format = SceneFormat::from_path(path).unwrap_or_else(|| SceneFormat::sniff(&text));
+    if format != SceneFormat::Toml {
+        return;
+    }
+    let Ok(table) = text.parse::<toml::Table>() else {
+        return;
+    };
+    let Some(includes) = table.get("include").and_then(|v| v.as_array()) else {
+        return;
+    };
+    let dir = path.parent().unwrap_or(Path::new(""));
+    for include in includes.iter().filter_map(|v| v.as_str()) {
+        collect_files(&dir.join(include), seen, files);
+    }
+}
+
+/// What is known of a watched file: its modification time and size, or
+/// `None` when it does not exist.
+type Stamp = Option<(Option<SystemTime>, u64)>;
+
+fn stamp(path: &Path) -> Stamp {
+    let metadata = fs::metadata(path).ok()?;
+    Some((metadata.modified().ok(), metadata.len()))
+}
+
+/// Watches a scene file and the files it includes for changes.
+///
+/// The files are polled for their modification time and size, which works
+/// the same on every platform and for editors that save by replacing the
+/// file. The set of files is refreshed on every load, so that adding or
+/// removing an include is picked up.
+///
+/// ```no_run
+/// use scene_loader::watch::SceneWatcher;
+/// use std::time::Duration;
+///
+/// let mut watcher = SceneWatcher::new("scene.toml");
+/// loop {
+///     match watcher.load() {
+///         Ok((scene, _)) => println!("{} objects", scene.objects.len()),
+///         Err(err) => eprintln!("error: {err}"),
+///     }
+///     watcher.wait(Duration::from_millis(250));
+/// }
+/// ```
+pub struct SceneWatcher {
+    path: PathBuf,
+    files: Vec<(PathBuf, Stamp)>,
+}
+
+impl SceneWatcher {
+    /// Starts watching the scene at `path`. Nothing is loaded until `load`
+    /// is called.
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        SceneWatcher {
+            path: path.as_ref().to_path_buf(),
+            files: Vec::new(),
+        }
+    }
+
+    /// The scene file being watched.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The files watched since the last load, the scene file first.
+    pub fn files(&self) -> impl Iterator<Item = &Path> {
+        self.files.iter().map(|(path, _)| path.as_path())
+    }
+
+    /// Loads the scene like `load_scene_with_warnings` and watches the
+    /// files it was read from.
+    ///
+    /// The files are looked at before they are read, so a change made while
+    /// loading is reported by the next `changed`.
+    pub fn load(&mut self) -> Result<(SceneFile, Vec<MigrationWarning>), SceneError> {
+        self.files = scene_files(&self.path)
+            .into_iter()
+            .map(|path| {
+                let stamp = stamp(&path);
+                (path, stamp)
+            })
+            .collect();
+        load_scene_with_warnings(&self.path)
+    }
+
+    /// Returns whether any watched file was changed, created or removed
+    /// since the last load. Always true before the first load.
+    pub fn changed(&self) -> bool {
+        self.files.is_empty() || self.files.iter().any(|(path, old)| stamp(path) != *old)
+    }
+
+    /// Blocks until `changed` is true, looking at the files every
+    /// `interval`.
+    pub fn wait(&self, interval: Duration) {
+        while !self.changed() {
+            thread::sleep(interval);
+        }
+    }
+
+    /// Reloads the scene if any watched file changed since the last load.
+    pub fn poll(&mut self) -> Option<Result<(SceneFile, Vec<MigrationWarning>), SceneError>> {
+        self.changed().then(|| self.load())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCENE: &str = r#"
+        version = 2
+        include = ["shared/lights.toml"]
+
+        [[objects]]
+        type = "sphere"
+        position = [0, 0, 0]
+        radius = 1.0
+    "#;
+
+    const LIGHTS: &str = r#"
+        version = 2
+        include = ["../scene.toml", "missing.toml"]
+
+        [[lights]]
+        type = "point"
+        position = [-10, 10, -10]
+        intensity = [1, 1, 1]
+    "#;
+
+    #[test]
+    fn scene_files_ok() {
+        let dir = tempfile::tempdir().unwrap();
+        let scene = dir.path().join("scene.toml");
+        fs::create_dir(dir.path().join("shared")).unwrap();
+        fs::write(&scene, SCENE).unwrap();
+        fs::write(dir.path().join("shared/lights.toml"), LIGHTS).unwrap();
+
+        // the cycle back to the scene is listed once
+        let files = scene_files(&scene);
+        assert_eq!(
+            files,
+            [
+                scene.clone(),
+                dir.path().join("shared/lights.toml"),
+                dir.path().join("shared/missing.toml"),
+            ]
+        );
+
+        fs::write(&scene, "version = ").unwrap();
+        assert_eq!(scene_files(&scene), [scene]);
+    }
+
+    #[test]
+    fn scene_watcher_ok() {
+        let dir = tempfile::tempdir().unwrap();
+        let scene = dir.path().join("scene.toml");
+        let lights = dir.path().join("shared/lights.toml");
+        fs::create_dir(dir.path().join("shared")).unwrap();
+        fs::write(&scene, SCENE).unwrap();
+        fs::write(&lights, LIGHTS.replace(", \"missing.toml\"", "")).unwrap();
+
+        let mut watcher = SceneWatcher::new(&scene);
+        assert!(watcher.changed());
+        // the include cycle is reported, and both files are watched
+        assert!(matches!(
+            watcher.poll(),
+            Some(Err(SceneError::IncludeCycle(_)))
+        ));
+        assert_eq!(watcher.files().count(), 2);
+        assert!(!watcher.changed());
+        assert!(watcher.poll().is_none());
+
+        // the sizes differ, whatever the precision of modification times
+        fs::write(&lights, LIGHTS.replace("include", "# include")).unwrap();
+        assert!(watcher.changed());
+        watcher.wait(Duration::from_millis(1));
+        let (loaded, _) = watcher.poll().unwrap().unwrap();
+        assert_eq!((loaded.lights.len(), loaded.objects.len()), (1, 1));
+        assert!(!watcher.changed());
+
+        fs::remove_file(&lights).unwrap();
+        assert!(watcher.changed());
+        assert!(watcher.poll().unwrap().is_err());
+    }
+}
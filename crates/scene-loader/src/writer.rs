@@ -0,0 +1,166 @@
+use crate::error::SceneError;
+use crate::format::SceneFormat;
+use scene_types::SceneFile;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// Serializes a scene as TOML that `parse_toml_scene_from_str` reads back
+/// into an equal `SceneFile`.
+pub fn scene_to_toml_string(scene: &SceneFile) -> Result<String, SceneError> {
+    toml::to_string(scene).map_err(|err| SceneError::Serialize(err.to_string()))
+}
+
+/// Serializes a scene as pretty-printed JSON that
+/// `parse_json_scene_from_str` reads back into an equal `SceneFile`.
+pub fn scene_to_json_string(scene: &SceneFile) -> Result<String, SceneError> {
+    serde_json::to_string_pretty(scene).map_err(|err| SceneError::Serialize(err.to_string()))
+}
+
+/// Writes a scene in the given format.
+///
+/// Only TOML and JSON can be written: the book's YAML format cannot
+/// express every `SceneFile`.
+pub fn write_scene<W: Write>(
+    scene: &SceneFile,
+    writer: &mut W,
+    format: SceneFormat,
+) -> Result<(), SceneError> {
+    let text = match format {
+        SceneFormat::Toml => scene_to_toml_string(scene)?,
+        SceneFormat::Json => scene_to_json_string(scene)?,
+        SceneFormat::Yaml => {
+            return Err(SceneError::Unsupported("writing YAML scenes".to_string()));
+        }
+    };
+    writer.write_all(text.as_bytes())?;
+    Ok(())
+}
+
+/// Saves a scene to `path`, choosing the format from its extension.
+///
+/// Missing parent directories are not created.
+pub fn save_scene<P: AsRef<Path>>(scene: &SceneFile, path: P) -> Result<(), SceneError> {
+    let path = path.as_ref();
+    let format = SceneFormat::from_path(path).ok_or_else(|| {
+        SceneError::Unsupported(format!("scene file extension of {}", path.display()))
+    })?;
+    let mut writer = BufWriter::new(File::create(path)?);
+    write_scene(scene, &mut writer, format)?;
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json::parse_json_scene_from_str;
+    use crate::load_scene;
+    use crate::toml::parse_toml_scene_from_str;
+
+    const SCENE: &str = r#"
+        version = 2
+
+        [camera]
+        position = [0, 1.5, -5]
+        target = [0, 1, 0]
+        fov = 60.0
+
+        [ambient]
+        intensity = 0.4
+        color = [255, 255, 255]
+
+        [materials.matte]
+        specular-coefficient = 0.0
+        shininess = 10.0
+
+        [materials.dark-matte]
+        extend = "matte"
+        diffuse-coefficient = 0.3
+
+        [[lights]]
+        type = "point"
+        position = [-10, 10, -10]
+        intensity = [0.5, 0.5, 0.5]
+
+        [[lights]]
+        type = "point"
+        position = [10, 10, -10]
+        intensity = [0.1, 0, 0]
+
+        [[objects]]
+        type = "sphere"
+        position = [0, 0, 0]
+        radius = 1.0
+        material = "dark-matte"
+        color = [136, 8, 8]
+        transform = [
+            { scale = [1, 0.5, 1] },
+            { rotate_x = 0.3 },
+            { rotate_y = 0.7 },
+            { rotate_z = -1.1 },
+            { translate = [0.1, 0.2, 0.3] },
+            { shear = { xy = 0.1, xz = 0, yx = 0, yz = 0, zx = 0, zy = 0.2 } },
+            { matrix = [[1, 0, 0, 0], [0, 1, 0, 0], [0, 0, 1, 0.5], [0, 0, 0, 1]] },
+        ]
+
+        [[objects]]
+        type = "sphere"
+        position = [0, 0, 3]
+        radius = 0.3
+        material = {
+                ambient-coefficient = 0.1,
+                diffuse-coefficient = 0.9,
+                specular-coefficient = 0.7,
+                shininess = 200.0
+            }
+        color = [8, 136, 8]
+
+        [[objects]]
+        type = "sphere"
+        position = [-1, 0, 3]
+        radius = 0.25
+        material = { extend = "matte", ambient-coefficient = 0.2 }
+        color = [8, 8, 136]
+    "#;
+
+    #[test]
+    fn scene_toml_round_trip_ok() {
+        let scene = parse_toml_scene_from_str(SCENE).unwrap();
+        let text = scene_to_toml_string(&scene).unwrap();
+        assert_eq!(parse_toml_scene_from_str(&text).unwrap(), scene);
+        // serializing again is stable
+        assert_eq!(scene_to_toml_string(&scene).unwrap(), text);
+    }
+
+    #[test]
+    fn scene_json_round_trip_ok() {
+        let scene = parse_toml_scene_from_str(SCENE).unwrap();
+        let text = scene_to_json_string(&scene).unwrap();
+        assert_eq!(parse_json_scene_from_str(&text).unwrap(), scene);
+    }
+
+    #[test]
+    fn save_scene_ok() {
+        let scene = parse_toml_scene_from_str(SCENE).unwrap();
+        let dir = tempfile::tempdir().expect("create temp dir");
+        for name in ["scene.toml", "scene.json"] {
+            let path = dir.path().join(name);
+            save_scene(&scene, &path).unwrap();
+            assert_eq!(load_scene(&path).unwrap(), scene);
+        }
+    }
+
+    #[test]
+    fn save_scene_yields_error() {
+        let scene = parse_toml_scene_from_str(SCENE).unwrap();
+        let dir = tempfile::tempdir().expect("create temp dir");
+
+        let err = save_scene(&scene, dir.path().join("scene.yaml"));
+        assert!(matches!(err, Err(SceneError::Unsupported(_))));
+        let err = save_scene(&scene, dir.path().join("scene.txt"));
+        assert!(matches!(err, Err(SceneError::Unsupported(_))));
+        let err = save_scene(&scene, dir.path().join("missing/scene.toml"));
+        assert!(matches!(err, Err(SceneError::InvalidFile(_))));
+    }
+}
@@ -0,0 +1,557 @@
+//! Scenes in the YAML format of "The Ray Tracer Challenge".
+//!
+//! A scene is a list of items:
+//! - `add: camera` with `field-of-view` (radians), `from`, `to` and `up`,
+//! - `add: light` with `at` and an RGB `intensity`,
+//! - `add: sphere` and `add: plane` with an optional `material` and
+//!   `transform` list,
+//! - `define: <name>` with a `value` holding a material or a transform
+//!   list, where materials may `extend` an earlier definition.
+//!
+//! Definitions are resolved in order, as in the book. Material colors move
+//! to the objects that use them. The camera `width` and `height` only tell
+//! which way the field of view spans: across the larger of the two, which
+//! the scene's vertical field of view is computed from. `shadow` and the
+//! reflection and refraction properties are accepted but ignored. Other
+//! shapes, patterns and a tilted `up` vector are reported as unsupported.
+
+use crate::error::SceneError;
+use crate::validate::validate_scene;
+use scene_types::{
+    AmbientDef, CURRENT_VERSION, CameraDef, DEFAULT_COLOR, LightDef, LightKindDef, MaterialDef,
+    NamedMaterialDef, ObjectDef, SceneFile, ShapeDef, TransformDef,
+};
+use serde::Deserialize;
+use serde::de::{DeserializeOwned, IgnoredAny};
+use serde_yaml::{Mapping, Value};
+use std::collections::BTreeMap;
+
+/// Shapes of the book that the ray tracer cannot render yet.
+const UNSUPPORTED_SHAPES: [&str; 5] = ["cube", "cylinder", "cone", "group", "obj"];
+
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+struct CameraYaml {
+    field_of_view: f32,
+    from: [f32; 3],
+    to: [f32; 3],
+    up: Option<[f32; 3]>,
+    width: Option<f32>,
+    height: Option<f32>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LightYaml {
+    at: [f32; 3],
+    intensity: [f32; 3],
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ShapeYaml {
+    material: Option<MaterialRefYaml>,
+    #[serde(default)]
+    transform: Vec<TransformItemYaml>,
+    #[serde(rename = "shadow", default)]
+    _shadow: IgnoredAny,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum MaterialRefYaml {
+    Named(String),
+    Inline(MaterialYaml),
+}
+
+#[derive(Deserialize, Clone, Default)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+struct MaterialYaml {
+    color: Option<[f32; 3]>,
+    ambient: Option<f32>,
+    diffuse: Option<f32>,
+    specular: Option<f32>,
+    shininess: Option<f32>,
+    pattern: Option<IgnoredAny>,
+    #[serde(rename = "reflective", default)]
+    _reflective: IgnoredAny,
+    #[serde(rename = "transparency", default)]
+    _transparency: IgnoredAny,
+    #[serde(rename = "refractive-index", default)]
+    _refractive_index: IgnoredAny,
+}
+
+impl MaterialYaml {
+    /// Returns `self` with unset fields taken from `parent`.
+    fn over(self, parent: &MaterialYaml) -> MaterialYaml {
+        MaterialYaml {
+            color: self.color.or(parent.color),
+            ambient: self.ambient.or(parent.ambient),
+            diffuse: self.diffuse.or(parent.diffuse),
+            specular: self.specular.or(parent.specular),
+            shininess: self.shininess.or(parent.shininess),
+            pattern: self.pattern.or(parent.pattern),
+            ..self
+        }
+    }
+
+    /// The coefficients set by this material, as a scene material.
+    fn coefficients(&self) -> NamedMaterialDef {
+        NamedMaterialDef {
+            extend: None,
+            ambient_coeff: self.ambient,
+            diffuse_coeff: self.diffuse,
+            specular_coeff: self.specular,
+            shininess: self.shininess,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum TransformItemYaml {
+    Named(String),
+    Step(Vec<Value>),
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct DefineYaml {
+    define: String,
+    extend: Option<String>,
+    value: Value,
+}
+
+/// Definitions seen so far, by name.
+#[derive(Default)]
+struct Definitions {
+    materials: BTreeMap<String, MaterialYaml>,
+    transforms: BTreeMap<String, Vec<TransformDef>>,
+}
+
+/// Parses a scene in the book's YAML format and validates it.
+pub fn parse_yaml_scene_from_str(yaml_str: &str) -> Result<SceneFile, SceneError> {
+    let items: Vec<Mapping> = serde_yaml::from_str(yaml_str)?;
+
+    let mut defs = Definitions::default();
+    let mut camera: Option<CameraDef> = None;
+    let mut lights: Vec<LightDef> = Vec::new();
+    let mut objects: Vec<ObjectDef> = Vec::new();
+
+    for (i, mut item) in items.into_iter().enumerate() {
+        // items are numbered from 1 in error messages
+        let index = i + 1;
+        let invalid = |message: String| SceneError::InvalidYamlEntry { index, message };
+
+        if item.contains_key("define") {
+            let define: DefineYaml = from_item(item, index)?;
+            defs.add(define).map_err(invalid)?;
+            continue;
+        }
+
+        let kind = match item.remove("add") {
+            Some(Value::String(kind)) => kind,
+            _ => return Err(invalid("expected an `add` or `define` key".to_string())),
+        };
+        match kind.as_str() {
+            "camera" => camera = Some(build_camera(from_item(item, index)?, index)?),
+            "light" => lights.push(build_light(from_item(item, index)?)),
+            "sphere" => {
+                let sphere = ShapeDef::Sphere { radius: 1.0 };
+                objects.push(defs.build_shape(sphere, from_item(item, index)?, index)?);
+            }
+            "plane" => {
+                objects.push(defs.build_shape(ShapeDef::Plane, from_item(item, index)?, index)?);
+            }
+            shape if UNSUPPORTED_SHAPES.contains(&shape) => {
+                return Err(SceneError::Unsupported(format!(
+                    "YAML item {index}: {shape} shapes"
+                )));
+            }
+            other => return Err(invalid(format!("unknown item `{other}`"))),
+        }
+    }
+
+    let scene = SceneFile {
+        version: CURRENT_VERSION,
+        camera: camera
+            .ok_or_else(|| SceneError::Unsupported("a YAML scene needs a camera".to_string()))?,
+        // the book has no global ambient light, only per material ambient
+        ambient: AmbientDef {
+            intensity: 1.0,
+            color: [255, 255, 255],
+        },
+        materials: defs.named_materials(),
+        lights,
+        objects,
+        animation: None,
+    };
+    validate_scene(&scene)?;
+    Ok(scene)
+}
+
+fn from_item<T: DeserializeOwned>(item: Mapping, index: usize) -> Result<T, SceneError> {
+    serde_yaml::from_value(Value::Mapping(item)).map_err(|err| SceneError::InvalidYamlEntry {
+        index,
+        message: err.to_string(),
+    })
+}
+
+fn build_camera(camera: CameraYaml, index: usize) -> Result<CameraDef, SceneError> {
+    if let Some([x, y, z]) = camera.up
+        && !(x == 0.0 && z == 0.0 && y > 0.0)
+    {
+        return Err(SceneError::Unsupported(format!(
+            "YAML item {index}: camera up vector [{x}, {y}, {z}]"
+        )));
+    }
+    // the book spans the field of view across the larger image side
+    let (width, height) = (camera.width.unwrap_or(1.0), camera.height.unwrap_or(1.0));
+    if !(width > 0.0 && height > 0.0) {
+        return Err(SceneError::InvalidYamlEntry {
+            index,
+            message: format!("camera size must be positive, got {width}x{height}"),
+        });
+    }
+    let mut fov = camera.field_of_view;
+    if width > height {
+        fov = 2.0 * ((fov / 2.0).tan() * height / width).atan();
+    }
+    Ok(CameraDef {
+        position: camera.from,
+        target: camera.to,
+        fov: fov.to_degrees(),
+    })
+}
+
+fn build_light(light: LightYaml) -> LightDef {
+    LightDef {
+        kind: LightKindDef::Point,
+        position: light.at,
+        intensity: light.intensity,
+    }
+}
+
+fn color_to_u8(color: [f32; 3]) -> [u8; 3] {
+    color.map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8)
+}
+
+impl Definitions {
+    fn add(&mut self, define: DefineYaml) -> Result<(), String> {
+        let name = define.define;
+        match define.value {
+            Value::Sequence(items) => {
+                if define.extend.is_some() {
+                    return Err(format!("transform list `{name}` cannot extend"));
+                }
+                let items: Vec<TransformItemYaml> =
+                    serde_yaml::from_value(Value::Sequence(items)).map_err(|e| e.to_string())?;
+                let steps = self.build_transform(&items)?;
+                self.transforms.insert(name, steps);
+            }
+            Value::Mapping(value) if !value.contains_key("add") => {
+                let mut material: MaterialYaml =
+                    serde_yaml::from_value(Value::Mapping(value)).map_err(|e| e.to_string())?;
+                if let Some(parent) = &define.extend {
+                    let parent = self
+                        .materials
+                        .get(parent)
+                        .ok_or_else(|| format!("unknown material `{parent}`"))?;
+                    material = material.over(parent);
+                }
+                self.materials.insert(name, material);
+            }
+            _ => {
+                return Err(format!(
+                    "`{name}` is neither a material nor a transform list"
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    fn build_transform(&self, items: &[TransformItemYaml]) -> Result<Vec<TransformDef>, String> {
+        let mut res: Vec<TransformDef> = Vec::new();
+        for item in items {
+            match item {
+                TransformItemYaml::Named(name) => {
+                    let steps = self
+                        .transforms
+                        .get(name)
+                        .ok_or_else(|| format!("unknown transform `{name}`"))?;
+                    res.extend(steps.iter().cloned());
+                }
+                TransformItemYaml::Step(step) => res.push(build_transform_step(step)?),
+            }
+        }
+        Ok(res)
+    }
+
+    /// Builds an object of the given `kind` placed by its `transform`.
+    fn build_shape(
+        &self,
+        kind: ShapeDef,
+        shape: ShapeYaml,
+        index: usize,
+    ) -> Result<ObjectDef, SceneError> {
+        let invalid = |message: String| SceneError::InvalidYamlEntry { index, message };
+
+        let (material, yaml) = match shape.material {
+            None => (MaterialDef::default(), MaterialYaml::default()),
+            Some(MaterialRefYaml::Named(name)) => {
+                let yaml = self
+                    .materials
+                    .get(&name)
+                    .ok_or_else(|| invalid(format!("unknown material `{name}`")))?
+                    .clone();
+                (MaterialDef::Named(name), yaml)
+            }
+            Some(MaterialRefYaml::Inline(yaml)) => (MaterialDef::Inline(yaml.coefficients()), yaml),
+        };
+        if yaml.pattern.is_some() {
+            return Err(SceneError::Unsupported(format!(
+                "YAML item {index}: patterns"
+            )));
+        }
+
+        Ok(ObjectDef {
+            position: [0.0, 0.0, 0.0],
+            material,
+            color: yaml.color.map_or(DEFAULT_COLOR, color_to_u8),
+            transform: self.build_transform(&shape.transform).map_err(invalid)?,
+            shape: kind,
+        })
+    }
+
+    /// Converts every material definition into an entry of the scene's
+    /// `[materials]` table. Inheritance is already resolved.
+    fn named_materials(&self) -> BTreeMap<String, NamedMaterialDef> {
+        self.materials
+            .iter()
+            .map(|(name, m)| (name.clone(), m.coefficients()))
+            .collect()
+    }
+}
+
+/// Converts a `[operation, arguments...]` list into a transform step.
+fn build_transform_step(step: &[Value]) -> Result<TransformDef, String> {
+    let (op, args) = step
+        .split_first()
+        .ok_or_else(|| "empty transform step".to_string())?;
+    let op = op
+        .as_str()
+        .ok_or_else(|| "a transform step must start with its name".to_string())?;
+    let args: Vec<f32> = args
+        .iter()
+        .map(|v| v.as_f64().map(|v| v as f32))
+        .collect::<Option<_>>()
+        .ok_or_else(|| format!("`{op}` arguments must be numbers"))?;
+
+    let step = match (op, args.as_slice()) {
+        ("translate", &[x, y, z]) => TransformDef::Translate([x, y, z]),
+        ("scale", &[x, y, z]) => TransformDef::Scale([x, y, z]),
+        ("rotate-x", &[r]) => TransformDef::RotateX(r),
+        ("rotate-y", &[r]) => TransformDef::RotateY(r),
+        ("rotate-z", &[r]) => TransformDef::RotateZ(r),
+        ("shear", &[xy, xz, yx, yz, zx, zy]) => TransformDef::Shear(scene_types::ShearDef {
+            xy,
+            xz,
+            yx,
+            yz,
+            zx,
+            zy,
+        }),
+        ("translate" | "scale" | "rotate-x" | "rotate-y" | "rotate-z" | "shear", _) => {
+            return Err(format!(
+                "wrong number of arguments for `{op}`: {}",
+                args.len()
+            ));
+        }
+        _ => return Err(format!("unknown transform `{op}`")),
+    };
+    Ok(step)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::FRAC_PI_2;
+
+    const SCENE: &str = r#"
+- add: camera
+  width: 100
+  height: 50
+  field-of-view: 1.0471975512
+  from: [0, 1.5, -5]
+  to: [0, 1, 0]
+  up: [0, 1, 0]
+
+- add: light
+  at: [-10, 10, -10]
+  intensity: [1, 0.5, 0]
+
+- define: white-material
+  value:
+    color: [1, 1, 1]
+    diffuse: 0.7
+    ambient: 0.1
+    specular: 0.0
+    reflective: 0.1
+
+- define: blue-material
+  extend: white-material
+  value:
+    color: [0, 0, 1]
+
+- define: standard-transform
+  value:
+    - [translate, 1, -1, 1]
+    - [scale, 0.5, 0.5, 0.5]
+
+- define: large-object
+  value:
+    - standard-transform
+    - [scale, 3.5, 3.5, 3.5]
+
+- add: sphere
+  material: blue-material
+  transform:
+    - large-object
+    - [rotate-y, 0.5]
+
+- add: sphere
+  material:
+    color: [1, 0, 0]
+    shininess: 50
+  shadow: false
+
+- add: plane
+  material: white-material
+  transform:
+    - [translate, 0, -1, 0]
+"#;
+
+    #[test]
+    fn parse_yaml_scene_from_str_ok() {
+        let scene = parse_yaml_scene_from_str(SCENE).unwrap();
+
+        assert_eq!(scene.camera.position, [0.0, 1.5, -5.0]);
+        assert_eq!(scene.camera.target, [0.0, 1.0, 0.0]);
+        // 60° across the 100 pixel width, i.e. about 32.2° across the height
+        assert!((scene.camera.fov - 32.2042).abs() < 1e-4);
+
+        assert_eq!(scene.lights.len(), 1);
+        assert_eq!(scene.lights[0].intensity, [1.0, 0.5, 0.0]);
+
+        let blue = &scene.materials["blue-material"];
+        assert_eq!(blue.extend, None);
+        assert_eq!(blue.diffuse_coeff, Some(0.7));
+        assert_eq!(blue.specular_coeff, Some(0.0));
+        assert_eq!(blue.shininess, None);
+
+        assert_eq!(scene.objects.len(), 3);
+        let large = &scene.objects[0];
+        assert_eq!(
+            large.material,
+            MaterialDef::Named("blue-material".to_string())
+        );
+        assert_eq!(large.color, [0, 0, 255]);
+        assert_eq!(
+            large.transform,
+            vec![
+                TransformDef::Translate([1.0, -1.0, 1.0]),
+                TransformDef::Scale([0.5, 0.5, 0.5]),
+                TransformDef::Scale([3.5, 3.5, 3.5]),
+                TransformDef::RotateY(0.5),
+            ]
+        );
+        assert_eq!(large.shape, ShapeDef::Sphere { radius: 1.0 });
+
+        let small = &scene.objects[1];
+        assert_eq!(small.color, [255, 0, 0]);
+        assert_eq!(
+            small.material,
+            MaterialDef::Inline(NamedMaterialDef {
+                shininess: Some(50.0),
+                ..Default::default()
+            })
+        );
+        assert!(small.transform.is_empty());
+
+        let floor = &scene.objects[2];
+        assert_eq!(floor.shape, ShapeDef::Plane);
+        assert_eq!(floor.color, [255, 255, 255]);
+        assert_eq!(floor.transform, [TransformDef::Translate([0.0, -1.0, 0.0])]);
+    }
+
+    #[test]
+    fn build_camera_ok() {
+        let camera = |size: &str| {
+            let yaml =
+                format!("{{field-of-view: 1.0471975512, from: [0, 0, -5], to: [0, 0, 0]{size}}}");
+            build_camera(serde_yaml::from_str(&yaml).unwrap(), 1).map(|camera| camera.fov)
+        };
+        // a tall or square image keeps the field of view across its height
+        assert!((camera("").unwrap() - 60.0).abs() < 1e-4);
+        assert!((camera(", width: 50, height: 100").unwrap() - 60.0).abs() < 1e-4);
+        assert!((camera(", width: 160, height: 120").unwrap() - 46.8264).abs() < 1e-4);
+        assert!(matches!(
+            camera(", width: 0, height: 100"),
+            Err(SceneError::InvalidYamlEntry { index: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn build_transform_step_ok() {
+        let step = |yaml: &str| {
+            let values: Vec<Value> = serde_yaml::from_str(yaml).unwrap();
+            build_transform_step(&values)
+        };
+        assert_eq!(
+            step("[rotate-x, 1.5707964]"),
+            Ok(TransformDef::RotateX(FRAC_PI_2))
+        );
+        assert!(matches!(
+            step("[shear, 1, 0, 0, 0, 0, 0]"),
+            Ok(TransformDef::Shear(_))
+        ));
+        assert!(step("[scale, 1, 2]").is_err());
+        assert!(step("[skew, 1]").is_err());
+        assert!(step("[translate, a, 1, 2]").is_err());
+    }
+
+    #[test]
+    fn parse_yaml_scene_from_str_yields_error() {
+        let err = parse_yaml_scene_from_str("- add: camera\n  from: [0, 0");
+        assert!(matches!(err, Err(SceneError::InvalidYaml(_))));
+
+        let input = SCENE.replace("material: blue-material", "material: red-material");
+        let err = parse_yaml_scene_from_str(&input);
+        assert!(matches!(
+            err,
+            Err(SceneError::InvalidYamlEntry { index: 7, ref message })
+                if message == "unknown material `red-material`"
+        ));
+
+        let input = SCENE.replace("shadow: false", "shadows: false");
+        let err = parse_yaml_scene_from_str(&input);
+        assert!(matches!(
+            err,
+            Err(SceneError::InvalidYamlEntry { index: 8, .. })
+        ));
+
+        let input = format!("{SCENE}\n- add: cube\n");
+        let err = parse_yaml_scene_from_str(&input);
+        assert!(
+            matches!(err, Err(SceneError::Unsupported(ref m)) if m == "YAML item 10: cube shapes")
+        );
+
+        let input = SCENE.replace("up: [0, 1, 0]", "up: [-0.45, 1, 0]");
+        let err = parse_yaml_scene_from_str(&input);
+        assert!(matches!(err, Err(SceneError::Unsupported(_))));
+
+        let input = SCENE.replace("to: [0, 1, 0]", "to: [0, 1.5, -5]");
+        let err = parse_yaml_scene_from_str(&input);
+        assert!(matches!(err, Err(SceneError::Validation(_))));
+    }
+}
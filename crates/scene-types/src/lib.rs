@@ -1,79 +1,500 @@
-use serde::Deserialize;
+//! Definitions of the scene file format shared by the scene loader and the
+//! applications that render scenes.
+//!
+//! These types describe the current version of the format,
+//! `CURRENT_VERSION`. The scene loader upgrades files written for older
+//! versions before they are deserialized into them.
+//!
+//! Every section and most fields are optional. The defaults live here, in
+//! the `Default` implementations and constants below, so that every
+//! consumer agrees on them:
+//! - `camera`: at `[0, 0, -5]` looking at the origin with a 60° field of view.
+//! - `ambient`: none, i.e. an intensity of `0.0`.
+//! - `materials`, `lights` and `objects`: empty.
+//! - object `color`: white.
+//! - object `material`: the default material, with the coefficients of
+//!   `MaterialCustomDef::default()`.
+//! - `animation`: none, a still scene. Its `fps` defaults to 24 and track
+//!   `interpolation` to linear.
+//!
+//! Scene files may also define a `[vars]` table, and write numeric fields
+//! as expressions such as `"$radius * 2"` or `"deg(45)"`. The scene loader
+//! evaluates them before deserializing, so these types only hold numbers.
 
-#[derive(Deserialize, Debug, PartialEq, Clone)]
+use schemars::transform::{RecursiveTransform, Transform};
+use schemars::{JsonSchema, Schema, SchemaGenerator, json_schema};
+use serde::de::{self, MapAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::{Value, json};
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// The version of the scene format described by this crate.
+///
+/// - `1`: lights have a scalar `intensity` and an 8-bit `color`, and inline
+///   materials are tagged with `type = "default"` or `type = "custom"`.
+///   Files without a `version` are version 1.
+/// - `2`: lights have an RGB `intensity`, and inline materials are untagged
+///   tables with the same fields as `[materials]` entries.
+pub const CURRENT_VERSION: u32 = 2;
+
+/// The color of objects that do not set one.
+pub const DEFAULT_COLOR: [u8; 3] = [255, 255, 255];
+
+/// Returns the JSON Schema of scene files of the current version, with the
+/// ranges checked by the loader and the defaults of omitted fields.
+///
+/// Unlike the schema derived for `SceneFile`, it accepts the `vars` table,
+/// the `include` list and expression strings in numeric fields. Like the
+/// loader, it also accepts files of older versions and without a `version`,
+/// which the loader upgrades.
+pub fn scene_schema() -> Schema {
+    let mut schema = schemars::schema_for!(SceneFile);
+    RecursiveTransform(allow_expressions).transform(&mut schema);
+    let properties = schema
+        .get_mut("properties")
+        .and_then(Value::as_object_mut)
+        .expect("a struct schema has properties");
+    properties["version"] = json!({
+        "description": "The version of the format, 1 when omitted.",
+        "type": "integer",
+        "minimum": 1,
+        "maximum": CURRENT_VERSION,
+    });
+    properties.insert(
+        "vars".to_string(),
+        json!({
+            "description": "Variables that expressions refer to as `$name`, each a number or an expression.",
+            "type": "object",
+            "additionalProperties": { "type": ["number", "string"] },
+        }),
+    );
+    properties.insert(
+        "include".to_string(),
+        json!({
+            "description": "Files merged into the scene before it, relative to this file.",
+            "type": "array",
+            "items": { "type": "string" },
+        }),
+    );
+    if let Some(required) = schema.get_mut("required").and_then(Value::as_array_mut) {
+        required.retain(|name| name != "version");
+    }
+    schema
+}
+
+/// Lets a numeric schema also accept an expression string.
+fn allow_expressions(schema: &mut Schema) {
+    let Some(types) = schema.get_mut("type") else {
+        return;
+    };
+    let mut names: Vec<Value> = match types {
+        Value::String(_) => vec![types.clone()],
+        Value::Array(names) => names.clone(),
+        _ => return,
+    };
+    let numeric = names.iter().any(|t| t == "number" || t == "integer");
+    if numeric && !names.iter().any(|t| t == "string") {
+        names.push("string".into());
+        *types = names.into();
+    }
+}
+
+/// A scene file.
+#[derive(Serialize, Deserialize, JsonSchema, Debug, PartialEq, Clone)]
 #[serde(deny_unknown_fields)]
 pub struct SceneFile {
+    #[schemars(extend("const" = CURRENT_VERSION))]
+    pub version: u32,
+    #[serde(default)]
     pub camera: CameraDef,
+    #[serde(default)]
     pub ambient: AmbientDef,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub materials: BTreeMap<String, NamedMaterialDef>,
+    #[serde(default)]
     pub lights: Vec<LightDef>,
+    #[serde(default)]
     pub objects: Vec<ObjectDef>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub animation: Option<AnimationDef>,
 }
 
-#[derive(Deserialize, Debug, PartialEq, Clone)]
-#[serde(deny_unknown_fields)]
+impl Default for SceneFile {
+    /// An empty scene of the current version.
+    fn default() -> Self {
+        SceneFile {
+            version: CURRENT_VERSION,
+            camera: CameraDef::default(),
+            ambient: AmbientDef::default(),
+            materials: BTreeMap::new(),
+            lights: Vec::new(),
+            objects: Vec::new(),
+            animation: None,
+        }
+    }
+}
+
+/// The camera. Omitted fields take their value from `CameraDef::default()`.
+#[derive(Serialize, Deserialize, JsonSchema, Debug, PartialEq, Clone)]
+#[serde(default, deny_unknown_fields)]
 pub struct CameraDef {
     pub position: [f32; 3],
     pub target: [f32; 3],
+    /// The vertical field of view, in degrees.
+    #[schemars(extend("exclusiveMinimum" = 0, "exclusiveMaximum" = 180))]
     pub fov: f32,
 }
 
-#[derive(Deserialize, Debug, PartialEq, Clone)]
-#[serde(deny_unknown_fields)]
+impl Default for CameraDef {
+    /// A camera at `[0, 0, -5]` looking at the origin with a 60° field of view.
+    fn default() -> Self {
+        CameraDef {
+            position: [0.0, 0.0, -5.0],
+            target: [0.0, 0.0, 0.0],
+            fov: 60.0,
+        }
+    }
+}
+
+/// The global ambient light. Omitted fields take their value from
+/// `AmbientDef::default()`.
+#[derive(Serialize, Deserialize, JsonSchema, Debug, PartialEq, Clone)]
+#[serde(default, deny_unknown_fields)]
 pub struct AmbientDef {
+    #[schemars(range(min = 0))]
     pub intensity: f32,
     pub color: [u8; 3],
 }
 
-#[derive(Deserialize, Debug, PartialEq, Clone)]
+impl Default for AmbientDef {
+    /// No ambient light: white with an intensity of `0.0`.
+    fn default() -> Self {
+        AmbientDef {
+            intensity: 0.0,
+            color: [255, 255, 255],
+        }
+    }
+}
+
+/// A light source. `intensity` is a linear RGB color whose channels may
+/// exceed `1.0`.
+#[derive(Serialize, Deserialize, JsonSchema, Debug, PartialEq, Clone)]
 #[serde(deny_unknown_fields)]
 pub struct LightDef {
     #[serde(rename = "type")]
     pub kind: LightKindDef,
     pub position: [f32; 3],
-    pub intensity: f32,
-    pub color: [u8; 3],
+    #[schemars(inner(range(min = 0)))]
+    pub intensity: [f32; 3],
 }
 
-#[derive(Deserialize, Debug, PartialEq, Clone)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug, PartialEq, Clone)]
 #[serde(rename_all = "lowercase", deny_unknown_fields)]
 pub enum LightKindDef {
     Point,
 }
 
-#[derive(Deserialize, Debug, PartialEq, Clone)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug, PartialEq, Clone)]
 pub struct ObjectDef {
     pub position: [f32; 3],
+    #[serde(default)]
     pub material: MaterialDef,
+    #[serde(default = "default_color")]
     pub color: [u8; 3],
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub transform: Vec<TransformDef>,
     #[serde(flatten)]
     pub shape: ShapeDef,
 }
 
-#[derive(Deserialize, Debug, PartialEq, Clone)]
+fn default_color() -> [u8; 3] {
+    DEFAULT_COLOR
+}
+
+/// One step of an object transform, written as a single-key table such as
+/// `{ rotate_y = 0.785 }`. Angles are in radians and `matrix` is row-major.
+///
+/// Steps apply in list order, around the object's own center: after the
+/// shape's size (e.g. the sphere radius) and before `position`.
+#[derive(Serialize, Deserialize, JsonSchema, Debug, PartialEq, Clone)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+pub enum TransformDef {
+    Translate([f32; 3]),
+    Scale([f32; 3]),
+    RotateX(f32),
+    RotateY(f32),
+    RotateZ(f32),
+    Shear(ShearDef),
+    Matrix([[f32; 4]; 4]),
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, PartialEq, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ShearDef {
+    pub xy: f32,
+    pub xz: f32,
+    pub yx: f32,
+    pub yz: f32,
+    pub zx: f32,
+    pub zy: f32,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, PartialEq, Clone)]
 #[serde(tag = "type", rename_all = "lowercase", deny_unknown_fields)]
 pub enum ShapeDef {
-    Sphere { radius: f32 },
+    Sphere {
+        #[schemars(extend("exclusiveMinimum" = 0))]
+        radius: f32,
+    },
+    /// The plane through the object's `position` that faces up, along +y,
+    /// until its `transform` turns it.
+    Plane,
 }
 
-#[derive(Deserialize, Debug, PartialEq, Clone)]
-#[serde(tag = "type", rename_all = "lowercase", deny_unknown_fields)]
+/// The material of an object: either the name of an entry in the scene's
+/// `[materials]` table, or an inline table with the same fields.
+#[derive(Debug, PartialEq, Clone)]
 pub enum MaterialDef {
-    Default(MaterialEmptyDef),
-    Custom(MaterialCustomDef),
+    Inline(NamedMaterialDef),
+    Named(String),
 }
 
-#[derive(Deserialize, Debug, PartialEq, Clone)]
+impl Default for MaterialDef {
+    /// An empty inline table, i.e. the default material.
+    fn default() -> Self {
+        MaterialDef::Inline(NamedMaterialDef::default())
+    }
+}
+
+impl JsonSchema for MaterialDef {
+    fn schema_name() -> Cow<'static, str> {
+        "MaterialDef".into()
+    }
+
+    fn json_schema(generator: &mut SchemaGenerator) -> Schema {
+        json_schema!({
+            "description": "The name of an entry of `materials`, or an inline material.",
+            "anyOf": [
+                { "type": "string" },
+                generator.subschema_for::<NamedMaterialDef>(),
+            ]
+        })
+    }
+}
+
+impl Serialize for MaterialDef {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            MaterialDef::Inline(m) => m.serialize(serializer),
+            MaterialDef::Named(name) => serializer.serialize_str(name),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for MaterialDef {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct MaterialVisitor;
+
+        impl<'de> Visitor<'de> for MaterialVisitor {
+            type Value = MaterialDef;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a material name or an inline material table")
+            }
+
+            fn visit_str<E: de::Error>(self, name: &str) -> Result<MaterialDef, E> {
+                Ok(MaterialDef::Named(name.to_string()))
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<MaterialDef, A::Error> {
+                let inline =
+                    NamedMaterialDef::deserialize(de::value::MapAccessDeserializer::new(map))?;
+                Ok(MaterialDef::Inline(inline))
+            }
+        }
+
+        deserializer.deserialize_any(MaterialVisitor)
+    }
+}
+
+/// An entry of the `[materials]` table, or an inline material. Fields left
+/// out are inherited from the material named by `extend`, or from the
+/// default material.
+#[derive(Serialize, Deserialize, JsonSchema, Debug, PartialEq, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct NamedMaterialDef {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub extend: Option<String>,
+    #[serde(
+        rename = "ambient-coefficient",
+        skip_serializing_if = "Option::is_none"
+    )]
+    #[schemars(range(min = 0))]
+    pub ambient_coeff: Option<f32>,
+    #[serde(
+        rename = "diffuse-coefficient",
+        skip_serializing_if = "Option::is_none"
+    )]
+    #[schemars(range(min = 0))]
+    pub diffuse_coeff: Option<f32>,
+    #[serde(
+        rename = "specular-coefficient",
+        skip_serializing_if = "Option::is_none"
+    )]
+    #[schemars(range(min = 0))]
+    pub specular_coeff: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schemars(range(min = 0))]
+    pub shininess: Option<f32>,
+}
+
+/// Keyframe animation of a scene, rendered as `frames` numbered frames.
+///
+/// `fps` is not used to render frames; it is the playback rate of the
+/// videos and animated images made from them.
+#[derive(Serialize, Deserialize, JsonSchema, Debug, PartialEq, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct AnimationDef {
+    #[schemars(range(min = 1))]
+    pub frames: u32,
+    #[serde(default = "default_fps")]
+    #[schemars(extend("exclusiveMinimum" = 0))]
+    pub fps: f32,
+    #[serde(default)]
+    pub tracks: Vec<TrackDef>,
+}
+
+fn default_fps() -> f32 {
+    24.0
+}
+
+/// A value animated between keyframes.
+///
+/// `target` is the path of a numeric value of the scene, or of an array of
+/// numbers, such as `camera.position`, `objects[0].transform[1].rotate_y`,
+/// `lights[0].position` or `materials.matte.shininess`. Before its first
+/// keyframe and after its last one, the value holds still.
+#[derive(Serialize, Deserialize, JsonSchema, Debug, PartialEq, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct TrackDef {
+    pub target: String,
+    #[serde(default)]
+    pub interpolation: InterpolationDef,
+    pub keyframes: Vec<KeyframeDef>,
+}
+
+/// How a track moves from one keyframe to the next.
+#[derive(Serialize, Deserialize, JsonSchema, Debug, PartialEq, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum InterpolationDef {
+    /// At a constant rate.
+    #[default]
+    Linear,
+    /// Easing in and out of every keyframe.
+    Smooth,
+}
+
+/// The value of a track at a frame, counted from 1.
+#[derive(Serialize, Deserialize, JsonSchema, Debug, PartialEq, Clone)]
 #[serde(deny_unknown_fields)]
+pub struct KeyframeDef {
+    #[schemars(range(min = 1))]
+    pub frame: u32,
+    pub value: KeyValueDef,
+}
+
+/// A keyframe value: a number, or an array of numbers such as a position.
+#[derive(Serialize, Deserialize, JsonSchema, Debug, PartialEq, Clone)]
+#[serde(untagged)]
+pub enum KeyValueDef {
+    Scalar(f32),
+    Vector(Vec<f32>),
+}
+
+/// The Phong coefficients of a fully resolved material.
+#[derive(Debug, PartialEq, Clone)]
 pub struct MaterialCustomDef {
-    #[serde(rename = "ambient-coefficient")]
     pub ambient_coeff: f32,
-    #[serde(rename = "diffuse-coefficient")]
     pub diffuse_coeff: f32,
-    #[serde(rename = "specular-coefficient")]
     pub specular_coeff: f32,
     pub shininess: f32,
 }
 
-#[derive(Deserialize, Debug, PartialEq, Clone)]
-#[serde(deny_unknown_fields)]
-pub struct MaterialEmptyDef {}
+impl Default for MaterialCustomDef {
+    /// The default material: ambient `0.1`, diffuse `0.9`, specular `0.9`
+    /// and shininess `200.0`, as in the book.
+    fn default() -> Self {
+        MaterialCustomDef {
+            ambient_coeff: 0.1,
+            diffuse_coeff: 0.9,
+            specular_coeff: 0.9,
+            shininess: 200.0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn scene_schema_ok() {
+        let schema = scene_schema();
+        let schema = schema.as_value();
+        let defs = &schema["$defs"];
+
+        let version = &schema["properties"]["version"];
+        assert_eq!(version["type"], "integer");
+        assert_eq!(version["minimum"], 1);
+        assert_eq!(version["maximum"], CURRENT_VERSION);
+        assert!(
+            !schema["required"]
+                .as_array()
+                .is_some_and(|required| required.contains(&json!("version")))
+        );
+        assert_eq!(
+            schema["properties"]["vars"]["additionalProperties"]["type"],
+            json!(["number", "string"])
+        );
+        assert_eq!(schema["properties"]["include"]["type"], "array");
+        assert_eq!(schema["properties"]["include"]["items"]["type"], "string");
+        assert_eq!(
+            schema["properties"]["camera"]["default"],
+            serde_json::to_value(CameraDef::default()).unwrap()
+        );
+
+        let fov = &defs["CameraDef"]["properties"]["fov"];
+        assert_eq!(fov["exclusiveMinimum"], 0);
+        assert_eq!(fov["exclusiveMaximum"], 180);
+        assert_eq!(fov["type"], json!(["number", "string"]));
+        assert_eq!(
+            defs["LightDef"]["properties"]["intensity"]["items"]["minimum"],
+            0
+        );
+
+        let object = &defs["ObjectDef"];
+        assert_eq!(
+            object["properties"]["color"]["default"],
+            json!(DEFAULT_COLOR)
+        );
+        assert_eq!(object["properties"]["material"]["default"], json!({}));
+        let sphere = &object["oneOf"][0]["properties"];
+        assert_eq!(sphere["type"]["const"], "sphere");
+        assert_eq!(sphere["radius"]["exclusiveMinimum"], 0);
+
+        assert_eq!(
+            defs["MaterialDef"]["anyOf"],
+            json!([
+                { "type": "string" },
+                { "$ref": "#/$defs/NamedMaterialDef" },
+            ])
+        );
+        assert_eq!(
+            defs["TransformDef"]["oneOf"][0]["required"],
+            json!(["translate"])
+        );
+    }
+}